@@ -1,3 +1,8 @@
 fn main() {
-    println!("cargo:rustc-link-lib=mimalloc");
+    // mimalloc is only linked when it's used as the global allocator
+    if std::env::var_os("CARGO_FEATURE_MIMALLOC").is_some()
+        && std::env::var_os("CARGO_FEATURE_SYSTEM_ALLOC").is_none()
+    {
+        println!("cargo:rustc-link-lib=mimalloc");
+    }
 }
@@ -0,0 +1,33 @@
+use crate::structs::Search;
+use std::time::{Duration, Instant};
+
+impl Search {
+    /// Runs the search `runs` times and prints the wall times to stderr, discarding the results.
+    ///
+    /// An extra first run is done to warm the filesystem cache, which is reported separately.
+    pub fn benchmark(&self, runs: usize) {
+        let time = || {
+            let start = Instant::now();
+            let buffers = self.search();
+            let elapsed = start.elapsed();
+            drop(buffers);
+            elapsed
+        };
+
+        let warmup = time();
+        let mut times = (0..runs).map(|_| time()).collect::<Vec<_>>();
+        times.sort();
+
+        eprintln!("Warmup:  {}", format_duration(warmup));
+        if let (Some(min), Some(max)) = (times.first(), times.last()) {
+            eprintln!("Runs:    {runs}");
+            eprintln!("Min:     {}", format_duration(*min));
+            eprintln!("Median:  {}", format_duration(times[times.len() / 2]));
+            eprintln!("Max:     {}", format_duration(*max));
+        }
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,90 @@
+use std::{
+    fs::{FileType, Metadata},
+    path::PathBuf,
+};
+
+/// A filesystem entry that can be checked against the search.
+///
+/// Implemented for the entries returned by `read_dir` and for paths that are not read from a directory (e.g. the search roots).
+pub trait Candidate {
+    fn path(&self) -> PathBuf;
+    /// Type of the entry, without following symlinks.
+    fn file_type(&self) -> std::io::Result<FileType>;
+    /// Metadata of the entry, without following symlinks.
+    fn metadata(&self) -> std::io::Result<Metadata>;
+    /// Inode number of the entry (the file index on Windows), if the platform has them.
+    fn inode(&self) -> Option<u64> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.metadata().ok().map(|m| m.ino())
+        }
+        #[cfg(windows)]
+        {
+            let handle = winapi_util::Handle::from_path_any(self.path()).ok()?;
+            winapi_util::file::information(&handle)
+                .ok()
+                .map(|i| i.file_index())
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            None
+        }
+    }
+    /// Device and inode numbers of the entry (the volume serial number and file index on Windows), which identify the file.
+    fn file_id(&self) -> Option<(u64, u64)> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.metadata().ok().map(|m| (m.dev(), m.ino()))
+        }
+        #[cfg(windows)]
+        {
+            let handle = winapi_util::Handle::from_path_any(self.path()).ok()?;
+            winapi_util::file::information(&handle)
+                .ok()
+                .map(|i| (i.volume_serial_number(), i.file_index()))
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            None
+        }
+    }
+}
+
+impl Candidate for std::fs::DirEntry {
+    fn path(&self) -> PathBuf {
+        self.path()
+    }
+    fn file_type(&self) -> std::io::Result<FileType> {
+        self.file_type()
+    }
+    fn metadata(&self) -> std::io::Result<Metadata> {
+        self.metadata()
+    }
+}
+
+/// A path with its already read metadata.
+pub struct PathCandidate {
+    path: PathBuf,
+    metadata: Metadata,
+}
+
+impl PathCandidate {
+    pub fn new(path: PathBuf) -> std::io::Result<Self> {
+        let metadata = path.symlink_metadata()?;
+        Ok(Self { path, metadata })
+    }
+}
+
+impl Candidate for PathCandidate {
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+    fn file_type(&self) -> std::io::Result<FileType> {
+        Ok(self.metadata.file_type())
+    }
+    fn metadata(&self) -> std::io::Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+}
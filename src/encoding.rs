@@ -0,0 +1,166 @@
+use std::io::Write;
+
+/// Encoding of the printed results, set with `--output-encoding`.
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Encoding {
+    /// Print the paths as they are
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    /// Only ASCII characters
+    Ascii,
+    /// ISO-8859-1, the first 256 Unicode characters
+    #[value(name = "iso-8859-1", alias = "latin1")]
+    Latin1,
+    /// Windows-1252, the Western European codepage of legacy Windows consoles
+    #[value(name = "windows-1252", alias = "cp1252")]
+    Windows1252,
+}
+
+/// Characters from 0x80 to 0x9F in Windows-1252, the rest are the same as in ISO-8859-1.
+///
+/// The unassigned bytes are mapped to `\0`, so they are never produced.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\0', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\0', 'Ž', '\0', //
+    '\0', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\0', 'ž', 'Ÿ',
+];
+
+impl Encoding {
+    /// Returns the byte of `c` in this single-byte encoding, if it can be represented.
+    fn encode(self, c: char) -> Option<u8> {
+        match self {
+            Encoding::Utf8 => unreachable!("UTF-8 is written without transcoding"),
+            _ if c.is_ascii() => Some(c as u8),
+            Encoding::Ascii => None,
+            Encoding::Latin1 => u8::try_from(c).ok(),
+            Encoding::Windows1252 => match WINDOWS_1252.iter().position(|&w| w == c) {
+                Some(i) => Some(0x80 + i as u8),
+                None => u8::try_from(c).ok().filter(|&b| b >= 0xA0),
+            },
+        }
+    }
+}
+
+/// Writer that transcodes the UTF-8 written to it, replacing the characters that can't be encoded with `?`.
+pub struct Encoder<W: Write> {
+    inner: W,
+    encoding: Encoding,
+    /// Bytes of a character split between two writes.
+    pending: Vec<u8>,
+    /// If the replaced characters must be reported, which is done only once.
+    verbose: bool,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, encoding: Encoding, verbose: bool) -> Self {
+        Self {
+            inner,
+            encoding,
+            pending: Vec::new(),
+            verbose,
+        }
+    }
+
+    fn replaced(&mut self) -> u8 {
+        if self.verbose {
+            let name = clap::ValueEnum::to_possible_value(&self.encoding)
+                .map(|v| v.get_name().to_owned())
+                .unwrap_or_default();
+            eprintln!(
+                "Warning: Some characters can't be printed with the '{name}' encoding, they are replaced with '?'"
+            );
+            self.verbose = false;
+        }
+        b'?'
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let pending = std::mem::take(&mut self.pending);
+        let mut out = Vec::with_capacity(pending.len());
+        let mut rest = pending.as_slice();
+        loop {
+            let (valid, error) = match std::str::from_utf8(rest) {
+                Ok(valid) => (valid, None),
+                Err(e) => (
+                    // SAFETY: the bytes up to `valid_up_to` are valid UTF-8
+                    unsafe { std::str::from_utf8_unchecked(&rest[..e.valid_up_to()]) },
+                    Some(e),
+                ),
+            };
+            for c in valid.chars() {
+                let byte = match self.encoding.encode(c) {
+                    Some(byte) => byte,
+                    None => self.replaced(),
+                };
+                out.push(byte);
+            }
+            match error {
+                None => break,
+                // A character split at the end, completed by the next write
+                Some(e) if e.error_len().is_none() => {
+                    self.pending = rest[e.valid_up_to()..].to_vec();
+                    break;
+                }
+                Some(e) => {
+                    let invalid = self.replaced();
+                    out.push(invalid);
+                    rest = &rest[e.valid_up_to() + e.error_len().unwrap_or(1)..];
+                }
+            }
+        }
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(encoding: Encoding, s: &str) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new(), encoding, false);
+        encoder.write_all(s.as_bytes()).unwrap();
+        encoder.inner
+    }
+
+    #[test]
+    fn non_ascii_path_to_a_single_byte_codepage() {
+        let path = "/home/josé/café €5.txt";
+        assert_eq!(
+            encode(Encoding::Latin1, path),
+            b"/home/jos\xe9/caf\xe9 ?5.txt"
+        );
+        let cp1252 = encode(Encoding::Windows1252, path);
+        assert_eq!(cp1252, b"/home/jos\xe9/caf\xe9 \x805.txt");
+        assert_eq!(encode(Encoding::Ascii, path), b"/home/jos?/caf? ?5.txt");
+    }
+
+    #[test]
+    fn unencodable_characters_are_replaced() {
+        assert_eq!(encode(Encoding::Latin1, "日本"), b"??");
+        // Unassigned in Windows-1252, even though it's a Latin-1 control character
+        assert_eq!(encode(Encoding::Windows1252, "\u{81}"), b"?");
+        assert_eq!(encode(Encoding::Latin1, "\u{81}"), b"\x81");
+    }
+
+    #[test]
+    fn characters_split_between_writes() {
+        let mut encoder = Encoder::new(Vec::new(), Encoding::Latin1, false);
+        let bytes = "é!".as_bytes();
+        encoder.write_all(&bytes[..1]).unwrap();
+        assert!(encoder.inner.is_empty());
+        encoder.write_all(&bytes[1..]).unwrap();
+        assert_eq!(encoder.inner, b"\xe9!");
+
+        // Invalid UTF-8 is replaced too
+        let mut encoder = Encoder::new(Vec::new(), Encoding::Latin1, false);
+        encoder.write_all(b"a\xffb").unwrap();
+        assert_eq!(encoder.inner, b"a?b");
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Status printed for the files without changes and the ones outside of a repository.
+pub const BLANK: [u8; 2] = *b"  ";
+
+/// Status of the files of a repository, as printed by `git status --porcelain`.
+struct Repo {
+    root: PathBuf,
+    /// Paths relative to `root` with their status, only the files with changes are listed.
+    statuses: HashMap<PathBuf, [u8; 2]>,
+}
+
+#[derive(Default)]
+struct Cache {
+    /// Repository of each directory with results, `None` if it's not inside one.
+    dirs: HashMap<PathBuf, Option<Arc<Repo>>>,
+    /// Repositories by their root, so `git` runs only once for each of them.
+    repos: HashMap<PathBuf, Option<Arc<Repo>>>,
+}
+
+/// Git status of the results, printed with `--git-status`.
+pub struct GitStatus {
+    cache: Mutex<Cache>,
+    verbose: bool,
+}
+
+impl GitStatus {
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            cache: Mutex::default(),
+            verbose,
+        }
+    }
+
+    /// Returns the two status letters of `path`, like `git status --short`, or [`BLANK`] if it has none.
+    pub fn of(&self, path: &Path) -> [u8; 2] {
+        let Ok(path) = path.canonicalize() else {
+            return BLANK;
+        };
+        let Some(repo) = path.parent().and_then(|dir| self.repo(dir)) else {
+            return BLANK;
+        };
+        let relative = path.strip_prefix(&repo.root).ok();
+        relative
+            .and_then(|path| repo.statuses.get(path).copied())
+            .unwrap_or(BLANK)
+    }
+
+    /// Returns the repository that contains `dir`, which must be canonical.
+    ///
+    /// The lock is held while `git` runs, so other threads wait for it instead of running it again.
+    fn repo(&self, dir: &Path) -> Option<Arc<Repo>> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(repo) = cache.dirs.get(dir) {
+            return repo.clone();
+        }
+        // A '.git' file is a worktree or a submodule, which are repositories too
+        let root = dir.ancestors().find(|d| d.join(".git").exists());
+        let repo = root.and_then(|root| {
+            let repo = cache.repos.entry(root.to_owned());
+            repo.or_insert_with(|| self.read(root)).clone()
+        });
+        cache.dirs.insert(dir.to_owned(), repo.clone());
+        repo
+    }
+
+    /// Runs `git status` in the repository at `root`.
+    fn read(&self, root: &Path) -> Option<Arc<Repo>> {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["status", "--porcelain", "-z", "--untracked-files=all"])
+            .output();
+        let output = match output {
+            Ok(output) if output.status.success() => output.stdout,
+            Ok(output) => {
+                if self.verbose {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    eprintln!("Could not read git status of {:?}: {}", root, stderr.trim());
+                }
+                return None;
+            }
+            Err(e) => {
+                if self.verbose {
+                    eprintln!("Could not run git in {:?}: {e}", root);
+                }
+                return None;
+            }
+        };
+        let mut statuses = HashMap::new();
+        // Each entry is 'XY PATH', renames and copies are followed by their original path
+        let mut entries = output.split(|&b| b == 0);
+        while let Some(entry) = entries.next() {
+            if entry.len() < 4 {
+                continue;
+            }
+            let status = [entry[0], entry[1]];
+            if matches!(status[0], b'R' | b'C') {
+                entries.next();
+            }
+            let path = PathBuf::from(String::from_utf8_lossy(&entry[3..]).as_ref());
+            statuses.insert(path, status);
+        }
+        Some(Arc::new(Repo {
+            root: root.to_owned(),
+            statuses,
+        }))
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::Path;
+
+/// Rules loaded with `--ignore-file` or `--glob-from`, using a gitignore-like syntax.
+///
+/// - Empty lines and lines starting with `#` are skipped.
+/// - A leading `!` negates the rule, so previously matched entries are excluded again.
+/// - A trailing `/` makes the rule only match directories.
+/// - Rules without a `/` are matched against the file name, the others against the whole path.
+///
+/// As in gitignore, the last matching rule wins.
+/// Rules are applied globally, regardless of the location of the file that contains them.
+pub struct IgnoreFile {
+    names: globset::GlobSet,
+    paths: globset::GlobSet,
+    /// Rules of `names` followed by the rules of `paths`, in the same order.
+    rules: Vec<Rule>,
+    /// Number of rules in `names`.
+    names_len: usize,
+}
+
+struct Rule {
+    /// Position of the rule in the files, used to decide which rule wins.
+    order: usize,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreFile {
+    /// Reads and compiles the rules of all `files`, in order.
+    ///
+    /// With `literal_separator`, `*` and `?` never match a path separator, only `**` does.
+    pub fn new(files: &[std::path::PathBuf], literal_separator: bool) -> Result<Self, String> {
+        let mut names = (globset::GlobSetBuilder::new(), Vec::new());
+        let mut paths = (globset::GlobSetBuilder::new(), Vec::new());
+        let mut order = 0;
+        for file in files {
+            let content = std::fs::read_to_string(file)
+                .map_err(|e| format!("Could not read {:?}: {e}", file))?;
+            for line in content.lines() {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(line) => (true, line),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(line) => (true, line),
+                    None => (false, line),
+                };
+                let (set, pattern) = if line.contains('/') {
+                    let line = line.trim_start_matches('/');
+                    (&mut paths, format!("**/{line}"))
+                } else {
+                    (&mut names, line.to_owned())
+                };
+                let glob = globset::GlobBuilder::new(&pattern)
+                    .literal_separator(literal_separator)
+                    .build()
+                    .map_err(|e| format!("Invalid pattern in {:?}: {e}", file))?;
+                set.0.add(glob);
+                set.1.push(Rule {
+                    order,
+                    negated,
+                    dir_only,
+                });
+                order += 1;
+            }
+        }
+        let build = |b: globset::GlobSetBuilder| b.build().map_err(|e| e.to_string());
+        let names_len = names.1.len();
+        let mut rules = names.1;
+        rules.extend(paths.1);
+        Ok(IgnoreFile {
+            names: build(names.0)?,
+            paths: build(paths.0)?,
+            rules,
+            names_len,
+        })
+    }
+
+    /// Returns true if the last rule matching the entry at `path` is not negated.
+    pub fn matches(&self, path: &Path, name: &std::ffi::OsStr, is_dir: bool) -> bool {
+        let names = self.names.matches(name).into_iter();
+        let paths = self
+            .paths
+            .matches(path)
+            .into_iter()
+            .map(|i| i + self.names_len);
+        names
+            .chain(paths)
+            .map(|i| &self.rules[i])
+            .filter(|rule| is_dir || !rule.dir_only)
+            .max_by_key(|rule| rule.order)
+            .is_some_and(|rule| !rule.negated)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    searchresult::Entry,
+    structs::{Output, Search},
+};
+use std::io::{BufRead, IsTerminal, Write};
+
+impl Search {
+    /// Searches once and keeps all the results, then prints the ones matching each query read from stdin.
+    ///
+    /// The queries are matched against the file names like in `is_result`, so no directory is read again.
+    pub fn interactive(&self) -> std::io::Result<()> {
+        let (ex, co, _) = self.search();
+        let mut index = ex;
+        index.extend(co);
+        index.sort_by(|a, b| self.compare(a, b));
+
+        let prompt = std::io::stdin().is_terminal();
+        if prompt {
+            eprintln!(
+                "Indexed {} entries, type a query and press Enter (Ctrl-D to exit)",
+                index.len()
+            );
+        }
+
+        let mut lines = std::io::stdin().lock().lines();
+        loop {
+            if prompt {
+                eprint!("> ");
+            }
+            let Some(query) = lines.next().transpose()? else {
+                break;
+            };
+            self.print_query(&index, &query)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the entries of `index` whose name matches `query`.
+    fn print_query(&self, index: &[Entry], query: &str) -> std::io::Result<()> {
+        let case_sensitive = self.case_sensitive
+            || (self.smart_case && query.contains(|c: char| c.is_alphabetic() && c.is_uppercase()));
+        let query = if case_sensitive {
+            query.to_owned()
+        } else {
+            query.to_ascii_lowercase()
+        };
+        let finder = memchr::memmem::Finder::new(query.as_bytes());
+
+        let mut exact = Vec::new();
+        let mut contains = Vec::new();
+        for entry in index {
+            let Some(name) = crate::search::file_name(std::path::Path::new(entry.path.as_str()))
+            else {
+                continue;
+            };
+            let name = name.to_string_lossy();
+            let name = if case_sensitive {
+                name
+            } else {
+                name.to_ascii_lowercase().into()
+            };
+            if finder.find(name.as_bytes()).is_none() {
+                continue;
+            }
+            if name.len() == query.len() {
+                exact.push(entry);
+            } else if !self.exact {
+                contains.push(entry);
+            }
+        }
+
+        let mut stdout = self.writer();
+        if exact.is_empty() && contains.is_empty() {
+            if self.output == Output::Normal {
+                writeln!(stdout, "File not found")?;
+            }
+            return stdout.flush();
+        }
+        if self.output == Output::Normal {
+            writeln!(stdout, "Contains:")?;
+        }
+        for entry in contains {
+            writeln!(stdout, "{}", self.display(entry))?;
+        }
+        if self.output == Output::Normal {
+            writeln!(stdout, "\nExact:")?;
+        }
+        for entry in exact {
+            writeln!(stdout, "{}", self.display(entry))?;
+        }
+        stdout.flush()
+    }
+}
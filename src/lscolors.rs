@@ -0,0 +1,91 @@
+/// Colors parsed from the `LS_COLORS` environment variable.
+///
+/// Only the keys that can be resolved without extra syscalls are supported, plus `ex` (executables) on Unix.
+pub struct LsColors {
+    /// `*.ext` patterns, sorted by length so the longest suffix wins (e.g. `*.tar.gz` over `*.gz`).
+    suffixes: Vec<(String, String)>,
+    dir: Option<String>,
+    link: Option<String>,
+    exec: Option<String>,
+    file: Option<String>,
+}
+
+impl LsColors {
+    /// Reads and parses `LS_COLORS`.
+    ///
+    /// Returns `None` if the variable is unset or no valid entry could be found.
+    pub fn from_env() -> Option<Self> {
+        Self::parse(&std::env::var("LS_COLORS").ok()?)
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut colors = LsColors {
+            suffixes: Vec::new(),
+            dir: None,
+            link: None,
+            exec: None,
+            file: None,
+        };
+        let mut valid = false;
+        for (key, style) in s.split(':').filter_map(|e| e.split_once('=')) {
+            // Malformed entries are skipped, an SGR sequence can only contain digits and ';'
+            if style.is_empty() || !style.bytes().all(|b| b.is_ascii_digit() || b == b';') {
+                continue;
+            }
+            let style = style.to_owned();
+            match key {
+                "di" => colors.dir = Some(style),
+                "ln" => colors.link = Some(style),
+                "ex" => colors.exec = Some(style),
+                "fi" => colors.file = Some(style),
+                _ => match key.strip_prefix('*') {
+                    Some(suffix) if !suffix.is_empty() => {
+                        colors.suffixes.push((suffix.to_ascii_lowercase(), style))
+                    }
+                    _ => continue,
+                },
+            }
+            valid = true;
+        }
+        colors
+            .suffixes
+            .sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+        valid.then_some(colors)
+    }
+
+    /// Returns the SGR style that should be used for an entry named `name`.
+    pub fn style(&self, name: &str, entry: &impl crate::candidate::Candidate) -> Option<&str> {
+        let ftype = entry.file_type().ok()?;
+        if ftype.is_dir() {
+            return self.dir.as_deref();
+        }
+        if ftype.is_symlink() {
+            return self.link.as_deref();
+        }
+        #[cfg(unix)]
+        if self.exec.is_some() {
+            use std::os::unix::fs::PermissionsExt;
+            if matches!(entry.metadata(), Ok(m) if m.permissions().mode() & 0o111 != 0) {
+                return self.exec.as_deref();
+            }
+        }
+        let name = name.to_ascii_lowercase();
+        self.suffixes
+            .iter()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .map(|(_, style)| style.as_str())
+            .or(self.file.as_deref())
+    }
+}
+
+/// Text painted with an optional `LS_COLORS` style.
+pub struct Painted<'a>(pub Option<&'a str>, pub &'a str);
+
+impl std::fmt::Display for Painted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(style) if !self.1.is_empty() => write!(f, "\x1b[{style}m{}\x1b[0m", self.1),
+            _ => f.write_str(self.1),
+        }
+    }
+}
@@ -1,9 +1,25 @@
+mod bench;
+mod candidate;
+mod encoding;
+mod gitstatus;
+mod ignorefile;
+mod interactive;
+mod lscolors;
 mod print;
+mod progress;
 mod search;
 mod searchresult;
+mod semaphore;
+mod skipped;
 mod structs;
+#[cfg(test)]
+mod testdir;
 
-#[cfg(not(any(test, miri)))]
+#[cfg(all(
+    feature = "mimalloc",
+    not(feature = "system-alloc"),
+    not(any(test, miri))
+))]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
@@ -11,8 +27,24 @@ fn main() -> std::io::Result<()> {
     profi::print_on_exit!(stderr);
     let search = structs::Cli::run();
 
+    if let Some(runs) = search.benchmark {
+        search.benchmark(runs);
+        return Ok(());
+    }
+
+    if search.interactive {
+        return search.interactive();
+    }
+
     let buffers = search.search();
+    let found = buffers.0.len() + buffers.1.len();
     search.print_results(buffers)?;
+    search.print_total_size()?;
+    search.print_skipped()?;
+    search.check_min_results(found);
 
-    Ok(())
+    match search.exit_code(found) {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
 }
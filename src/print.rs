@@ -1,77 +1,729 @@
-use crate::structs::{Buffers, Output, Search};
+use crate::{
+    encoding::{Encoder, Encoding},
+    searchresult::Entry,
+    structs::{Anchor, Buffers, Group, Highlight, Output, QuoteStyle, Search, SizeUnits, Sort},
+};
 use rayon::prelude::ParallelSliceMut;
 use std::io::Write;
 
 impl Search {
     #[profi::profile]
-    pub fn print_results(self, buffers: Buffers) -> std::io::Result<()> {
+    pub fn print_results(&self, buffers: Buffers) -> std::io::Result<()> {
         profi::prof!(print_results);
 
-        if self.output == Output::SuperSimple {
+        if self.truncated.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!(
+                "Warning: The results reached the memory limit of {}, some of them are not shown",
+                format_size(self.limit_memory.unwrap_or_default(), self.size_units)
+            );
+        }
+
+        // -ss results were already printed while searching
+        if self.output == Output::SuperSimple && !self.deterministic {
             return Ok(());
         }
 
-        let stdout = std::io::stdout();
-        let mut stdout = std::io::BufWriter::new(stdout.lock());
+        let mut stdout = self.writer();
 
-        let (mut ex, mut co) = buffers;
-        if ex.is_empty() && co.is_empty() {
+        let (mut ex, mut co, mut si) = buffers;
+        if ex.is_empty() && co.is_empty() && !self.summary_json {
             if self.output == Output::Normal {
                 writeln!(stdout, "File not found")?;
             }
             return Ok(());
         }
 
-        {
+        if self.count_by_extension {
+            return self.print_extension_counts(ex.iter().chain(&co), stdout);
+        }
+        if self.disk_usage {
+            return self.print_disk_usage(ex.iter().chain(&co), stdout);
+        }
+
+        let found = ex.len() + co.len();
+        if self.summarize_above.is_some_and(|n| found > n) {
+            return self.print_summary(&ex, &co, stdout);
+        }
+
+        let keep = self.newest.or(self.oldest);
+        // A single list sorted as a whole
+        if self.collapse || keep.is_some() {
+            co.append(&mut ex);
+        }
+
+        if let Some(n) = keep {
+            profi::prof!(keep_by_mtime);
+            let cmp = |a: &Entry, b: &Entry| self.compare_modified(a, b);
+            if co.len() > n {
+                co.select_nth_unstable_by(n, cmp);
+                co.truncate(n);
+            }
+            co.sort_unstable_by(cmp);
+        } else {
             profi::prof!(sort);
-            rayon::join(|| co.par_sort(), || ex.par_sort());
+            let cmp = |a: &Entry, b: &Entry| self.compare(a, b);
+            rayon::join(
+                || co.par_sort_by(cmp),
+                || rayon::join(|| ex.par_sort_by(cmp), || si.par_sort_by(cmp)),
+            );
         }
         
+        if self.summary_json {
+            return self.print_summary_json((ex, co, si), stdout);
+        }
         if self.select {
-            return select((ex, co), stdout);
+            return select((ex, co, si), stdout, self);
         }
         if self.multiselect {
-            return multiselect((ex, co), stdout);
+            return multiselect((ex, co, si), stdout, self);
         }
 
-        if self.output == Output::Normal {
+        let headers = self.output == Output::Normal
+            && !self.collapse
+            && keep.is_none()
+            && self.only.is_none();
+        if headers {
             writeln!(stdout, "Contains:")?;
         }
         for path in co.into_iter() {
-            writeln!(stdout, "{path}")?;
+            writeln!(stdout, "{}", self.display(&path))?;
         }
-        if self.output == Output::Normal {
+        if headers {
             writeln!(stdout, "\nExact:")?;
         }
         for path in ex.into_iter() {
-            writeln!(stdout, "{path}")?;
+            writeln!(stdout, "{}", self.display(&path))?;
+        }
+        if self.output == Output::Normal && !si.is_empty() {
+            writeln!(stdout, "\nSiblings:")?;
+        }
+        for path in si.into_iter() {
+            writeln!(stdout, "{}", self.display(&path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the number of files of each extension, from most to least common.
+    fn print_extension_counts<'a>(
+        &self,
+        entries: impl Iterator<Item = &'a Entry>,
+        mut stdout: impl Write,
+    ) -> std::io::Result<()> {
+        let mut counts = std::collections::HashMap::<String, usize>::new();
+        for entry in entries.filter(|e| !e.is_dir) {
+            let extension = std::path::Path::new(entry.path.as_str())
+                .extension()
+                .map_or_else(|| "(none)".into(), |e| e.to_string_lossy().into_owned());
+            *counts.entry(extension).or_default() += 1;
         }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_unstable_by(|(a, ac), (b, bc)| bc.cmp(ac).then_with(|| a.cmp(b)));
 
+        let width = counts.first().map_or(0, |(_, c)| c.to_string().len());
+        for (extension, count) in counts {
+            writeln!(stdout, "{count:>width$} {extension}")?;
+        }
         Ok(())
     }
+
+    /// Prints the sizes of the matched files added up in each of their directories, used instead of the results with `--du`.
+    ///
+    /// The sizes are added up to the search root of each file, so directories above it are not printed.
+    fn print_disk_usage<'a>(
+        &self,
+        entries: impl Iterator<Item = &'a Entry>,
+        mut stdout: impl Write,
+    ) -> std::io::Result<()> {
+        let mut sizes = std::collections::HashMap::<&std::path::Path, u64>::new();
+        for entry in entries {
+            let Some(size) = entry.extra().size else {
+                continue;
+            };
+            // At least one character is kept, so "/" is still a root and "" never is
+            let root = entry.path[..entry.root_len].trim_end_matches(std::path::is_separator);
+            let min_len = root.len().max(1);
+            let path = std::path::Path::new(entry.path.as_str());
+            let ancestors = path.ancestors().skip(1);
+            for dir in ancestors.take_while(|d| d.as_os_str().len() >= min_len) {
+                *sizes.entry(dir).or_default() += size;
+            }
+        }
+        let mut sizes = sizes.into_iter().collect::<Vec<_>>();
+        sizes.sort_unstable_by(|(a, asize), (b, bsize)| bsize.cmp(asize).then_with(|| a.cmp(b)));
+
+        for (dir, size) in sizes {
+            let size = format_size(size, self.size_units);
+            writeln!(stdout, "{size}\t{}", dir.display())?;
+        }
+        Ok(())
+    }
+
+    /// Prints the number of matches and the directories that contain most of them, used instead of the results with `--summarize-above`.
+    fn print_summary(
+        &self,
+        ex: &[Entry],
+        co: &[Entry],
+        mut stdout: impl Write,
+    ) -> std::io::Result<()> {
+        const TOP_DIRS: usize = 10;
+
+        let mut counts = std::collections::HashMap::<&std::path::Path, usize>::new();
+        for entry in ex.iter().chain(co) {
+            let parent = std::path::Path::new(entry.path.as_str())
+                .parent()
+                .unwrap_or(std::path::Path::new(""));
+            *counts.entry(parent).or_default() += 1;
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_unstable_by(|(a, ac), (b, bc)| bc.cmp(ac).then_with(|| a.cmp(b)));
+        counts.truncate(TOP_DIRS);
+
+        writeln!(
+            stdout,
+            "Found {} matches ({} exact, {} containing the query)",
+            ex.len() + co.len(),
+            ex.len(),
+            co.len()
+        )?;
+        writeln!(stdout, "\nTop directories:")?;
+        let width = counts.first().map_or(0, |(_, c)| c.to_string().len());
+        for (dir, count) in counts {
+            writeln!(stdout, "{count:>width$} {}", dir.display())?;
+        }
+        stdout.flush()?;
+        eprintln!(
+            "Too many results to print, narrow the query or raise '--summarize-above' to see them"
+        );
+        Ok(())
+    }
+
+    /// Prints the results and the stats of the search as a single JSON document, used with `--summary-json`.
+    fn print_summary_json(&self, buffers: Buffers, mut stdout: impl Write) -> std::io::Result<()> {
+        let (ex, co, si) = buffers;
+        let results = (co.iter().map(|e| ("contains", e)))
+            .chain(ex.iter().map(|e| ("exact", e)))
+            .chain(si.iter().map(|e| ("sibling", e)));
+        write!(stdout, "{{\"results\":[")?;
+        for (i, (kind, entry)) in results.enumerate() {
+            if i > 0 {
+                write!(stdout, ",")?;
+            }
+            write!(
+                stdout,
+                "{{\"path\":{},\"kind\":\"{kind}\",\"is_dir\":{}}}",
+                json_string(&entry.path),
+                entry.is_dir
+            )?;
+        }
+        let ordering = std::sync::atomic::Ordering::Relaxed;
+        let scanned = self.scanned_dirs.as_ref().map_or(0, |s| s.load(ordering));
+        write!(
+            stdout,
+            "],\"stats\":{{\"scanned_dirs\":{scanned},\"elapsed_ms\":{:.3},\"exact\":{},\"contains\":{},\"siblings\":{}",
+            self.started.elapsed().as_secs_f64() * 1000.0,
+            ex.len(),
+            co.len(),
+            si.len()
+        )?;
+        if let Some(total) = &self.total_size {
+            write!(stdout, ",\"total_size\":{}", total.load(ordering))?;
+        }
+        writeln!(stdout, "}}}}")
+    }
+
+    /// Prints the sum of the sizes of the matches, if `--total-size` is enabled.
+    pub fn print_total_size(&self) -> std::io::Result<()> {
+        let Some(total) = self.total_size.as_ref().filter(|_| !self.summary_json) else {
+            return Ok(());
+        };
+        let total = total.load(std::sync::atomic::Ordering::Relaxed);
+        let mut stdout = self.writer();
+        if self.output == Output::Normal {
+            writeln!(stdout)?;
+        }
+        let size = format_size(total, self.size_units);
+        writeln!(stdout, "Total size: {size} ({total} bytes)")
+    }
+
+    /// Prints the entries left out of the search and a count for each reason to stderr, if `--report-skipped` is enabled.
+    pub fn print_skipped(&self) -> std::io::Result<()> {
+        let Some(skipped) = &self.skipped else {
+            return Ok(());
+        };
+        let skipped = skipped.take();
+        let mut counts = std::collections::BTreeMap::new();
+        let mut stderr = std::io::stderr().lock();
+        for (path, reason) in &skipped {
+            writeln!(stderr, "Skipped {:?}: {reason}", path)?;
+            *counts.entry(*reason).or_insert(0usize) += 1;
+        }
+        writeln!(stderr, "Skipped {} entries", skipped.len())?;
+        for (reason, count) in counts {
+            writeln!(stderr, "{count:>8}  {reason}")?;
+        }
+        Ok(())
+    }
+
+    /// Order in which the results are printed.
+    ///
+    /// Directories are grouped before or after files if requested, then results are sorted by the `--sort` key and path.
+    pub fn compare(&self, a: &Entry, b: &Entry) -> std::cmp::Ordering {
+        let group = match self.group {
+            Group::Mixed => std::cmp::Ordering::Equal,
+            Group::DirsFirst => b.is_dir.cmp(&a.is_dir),
+            Group::FilesFirst => a.is_dir.cmp(&b.is_dir),
+        };
+        let by_path = || {
+            if self.natural_sort {
+                natural_cmp(&a.path, &b.path).then_with(|| a.cmp(b))
+            } else {
+                a.cmp(b)
+            }
+        };
+        let (a_time, b_time) = (a.extra().sort_time, b.extra().sort_time);
+        // Results whose time couldn't be read go last, even in reverse order
+        let missing_time = match self.sort {
+            Sort::Accessed | Sort::Created => a_time.is_none().cmp(&b_time.is_none()),
+            Sort::Name | Sort::Depth => std::cmp::Ordering::Equal,
+        };
+        let order = match self.sort {
+            Sort::Name => by_path(),
+            Sort::Depth => a.depth.cmp(&b.depth),
+            Sort::Accessed | Sort::Created => a_time.cmp(&b_time),
+        };
+        let order = if self.reverse { order.reverse() } else { order };
+        group.then(missing_time).then(order).then_with(by_path)
+    }
+
+    /// Order of the results kept by `--newest` and `--oldest`.
+    ///
+    /// Results whose modification time couldn't be read are always last.
+    fn compare_modified(&self, a: &Entry, b: &Entry) -> std::cmp::Ordering {
+        let order = match (a.extra().modified, b.extra().modified) {
+            (Some(a), Some(b)) if self.newest.is_some() => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        };
+        order.then_with(|| a.cmp(b))
+    }
+
+    /// Returns the writer the results must be printed to, the `--output-file` or stdout.
+    pub fn writer(&self) -> std::io::BufWriter<Box<dyn Write + '_>> {
+        let writer: Box<dyn Write + '_> = match &self.output_file {
+            Some(file) => Box::new(file),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        let writer: Box<dyn Write + '_> = match self.output_encoding {
+            Encoding::Utf8 => writer,
+            encoding => Box::new(Encoder::new(writer, encoding, self.verbose)),
+        };
+        match self.output_buffer_size {
+            Some(capacity) => std::io::BufWriter::with_capacity(capacity, writer),
+            None => std::io::BufWriter::new(writer),
+        }
+    }
+
+    /// Returns `entry` as it must be printed, with all the output options applied.
+    pub fn display<'a>(&'a self, entry: &'a Entry) -> EntryDisplay<'a> {
+        EntryDisplay {
+            search: self,
+            entry,
+        }
+    }
+
+    /// Returns the text that must be printed right after `entry`.
+    ///
+    /// If `--trailing-slash` is enabled, directories get a path separator appended, unless they already end with one (e.g. `/`).
+    fn suffix(&self, entry: &Entry) -> &'static str {
+        if self.trailing_slash && entry.is_dir && !entry.path.ends_with(std::path::MAIN_SEPARATOR) {
+            std::path::MAIN_SEPARATOR_STR
+        } else {
+            ""
+        }
+    }
+}
+
+pub struct EntryDisplay<'a> {
+    search: &'a Search,
+    entry: &'a Entry,
+}
+
+impl std::fmt::Display for EntryDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = &self.entry.path;
+        let suffix = self.search.suffix(self.entry);
+        let extra = self.entry.extra();
+        if self.search.show_depth {
+            write!(f, "{} ", self.entry.depth)?;
+        }
+        if self.search.inode {
+            match extra.inode {
+                Some(inode) => write!(f, "{inode} ")?,
+                None => write!(f, "- ")?,
+            }
+        }
+        if let Some(tag) = extra.type_tag.filter(|_| self.search.show_type) {
+            write!(f, "{tag} ")?;
+        }
+        if let Some([x, y]) = extra.git_status {
+            write!(f, "{}{} ", x as char, y as char)?;
+        }
+        // The search root itself is printed as "."
+        let path = if self.search.relative_roots && self.entry.root_len == path.len() {
+            std::borrow::Cow::Borrowed(".")
+        } else {
+            std::borrow::Cow::Borrowed(&path[self.entry.root_len..])
+        };
+        // The span is relative to the file name, which is always at the end of the path
+        let name_len = std::path::Path::new(path.as_ref())
+            .file_name()
+            .map_or(0, |n| n.len());
+        let (path, suffix) = match self.search.path_separator {
+            Some(separator) => (
+                std::borrow::Cow::Owned(replace_separator(&path, separator).into_owned()),
+                replace_separator(suffix, separator),
+            ),
+            None => (path, std::borrow::Cow::Borrowed(suffix)),
+        };
+        if let Some((start, end)) = extra.match_span.filter(|_| self.search.show_offsets) {
+            let name_start = path.len() - name_len;
+            write!(f, "{}:{}:", name_start + start, name_start + end)?;
+        }
+        let path = if self.entry.quoted {
+            quote(self.search.quote_style, &path)
+        } else {
+            path
+        };
+        match self.search.trim {
+            Some(width) => {
+                let separator = self.search.path_separator;
+                let separator = separator.unwrap_or(std::path::MAIN_SEPARATOR);
+                write!(f, "{}", trim(&format!("{path}{suffix}"), width, separator))
+            }
+            None => write!(f, "{path}{suffix}"),
+        }
+    }
+}
+
+/// Compares `a` and `b` as text, except for the runs of digits, which are compared by their value.
+///
+/// Numbers with the same value are equal regardless of their leading zeros, e.g. "file01" and "file1".
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    let digits = |s: &[u8]| s.iter().take_while(|c| c.is_ascii_digit()).count();
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (na, nb) = (digits(a), digits(b));
+                let zeros = |s: &[u8]| s.iter().take_while(|&&c| c == b'0').count();
+                let (va, vb) = (&a[zeros(&a[..na])..na], &b[zeros(&b[..nb])..nb]);
+                let order = va.len().cmp(&vb.len()).then_with(|| va.cmp(vb));
+                if order.is_ne() {
+                    return order;
+                }
+                (a, b) = (&a[na..], &b[nb..]);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                (a, b) = (&a[1..], &b[1..]);
+            }
+        }
+    }
+}
+
+/// Shortens `path` to `width` visible characters, replacing its middle with "..." and keeping the file name.
+///
+/// If the file name doesn't fit, the whole top of the path is replaced and the result is longer than `width`.
+/// The color escape sequences don't count as visible, and they are always kept so the colors stay balanced.
+fn trim(path: &str, width: usize, separator: char) -> std::borrow::Cow<'_, str> {
+    const ELLIPSIS: &str = "...";
+
+    let mut escape = false;
+    let visible = path
+        .chars()
+        .filter(|&c| is_visible(c, &mut escape))
+        .collect::<String>();
+    let len = visible.chars().count();
+    if len <= width {
+        return std::borrow::Cow::Borrowed(path);
+    }
+
+    let stem = visible.trim_end_matches(separator);
+    let name = stem.rsplit(separator).next().unwrap_or_default();
+    let available = width.saturating_sub(ELLIPSIS.len());
+    // The file name with the separator before it, and the trailing separators of directories
+    let tail = (len - stem.chars().count() + name.chars().count() + 1).min(len);
+    let head = available.saturating_sub(tail);
+    if head + ELLIPSIS.len() + tail >= len {
+        return std::borrow::Cow::Borrowed(path);
+    }
+
+    let mut trimmed = String::with_capacity(path.len());
+    let mut index = 0;
+    for c in path.chars() {
+        if !is_visible(c, &mut escape) {
+            trimmed.push(c);
+            continue;
+        }
+        if index == head {
+            trimmed.push_str(ELLIPSIS);
+        }
+        if index < head || index >= len - tail {
+            trimmed.push(c);
+        }
+        index += 1;
+    }
+    std::borrow::Cow::Owned(trimmed)
+}
+
+/// Returns true if `c` is printed, false if it's part of a color escape sequence.
+///
+/// `escape` keeps track of whether an escape sequence is open between calls.
+fn is_visible(c: char, escape: &mut bool) -> bool {
+    match c {
+        '\x1b' => *escape = true,
+        'm' if *escape => {
+            *escape = false;
+            return false;
+        }
+        _ => (),
+    }
+    !*escape
+}
+
+/// Prints the capabilities of this build as a single JSON object, used with `--capabilities`.
+///
+/// The options are read from the command line definition, so they are always up to date.
+pub fn print_capabilities() -> std::io::Result<()> {
+    let mut command = <crate::structs::Cli as clap::CommandFactory>::command();
+    command.build();
+    let options = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_long())
+        .map(json_string)
+        .collect::<Vec<_>>();
+    let mimalloc = cfg!(all(feature = "mimalloc", not(feature = "system-alloc")));
+    let mut stdout = std::io::stdout().lock();
+    writeln!(
+        stdout,
+        "{{\"name\":\"hunt\",\"version\":{},\"os\":{},\"family\":{},\"arch\":{},\"features\":{{\"mimalloc\":{mimalloc},\"perf\":{}}},\"options\":[{}]}}",
+        json_string(env!("CARGO_PKG_VERSION")),
+        json_string(std::env::consts::OS),
+        json_string(std::env::consts::FAMILY),
+        json_string(std::env::consts::ARCH),
+        cfg!(feature = "perf"),
+        options.join(",")
+    )
+}
+
+/// Returns `s` as a quoted JSON string.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            '\r' => json.push_str("\\r"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Replaces the platform path separators of `path` with `separator`, leaving the color escape sequences untouched.
+fn replace_separator(path: &str, separator: char) -> std::borrow::Cow<'_, str> {
+    if !path.contains(std::path::MAIN_SEPARATOR) {
+        return std::borrow::Cow::Borrowed(path);
+    }
+    let mut replaced = String::with_capacity(path.len());
+    let mut escape = false;
+    for c in path.chars() {
+        // Called for every character, to keep track of the escape sequences
+        let visible = is_visible(c, &mut escape);
+        if visible && c == std::path::MAIN_SEPARATOR {
+            replaced.push(separator);
+        } else {
+            replaced.push(c);
+        }
+    }
+    std::borrow::Cow::Owned(replaced)
+}
+
+/// Formats `bytes` with the `units`, e.g. "1.5 KiB" or "1.5 kB".
+///
+/// A size that would be rounded up to the next unit is printed in it, e.g. "1.0 MiB" instead of "1024.0 KiB".
+pub fn format_size(bytes: u64, units: SizeUnits) -> String {
+    let (base, units) = match units {
+        SizeUnits::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB"]),
+        SizeUnits::Decimal => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB"]),
+    };
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= base && unit < units.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+    if unit > 0 && size >= base - 0.05 && unit < units.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", units[unit])
+    }
+}
+
+/// Returns `path` as it must be printed with the quoting `style`.
+///
+/// Paths that don't need quoting are returned borrowed.
+pub fn quote(style: QuoteStyle, path: &str) -> std::borrow::Cow<'_, str> {
+    use std::fmt::Write;
+
+    let escape = |c: char, out: &mut String| {
+        let _ = match c {
+            '\n' => write!(out, "\\n"),
+            '\t' => write!(out, "\\t"),
+            '\r' => write!(out, "\\r"),
+            '\0' => write!(out, "\\0"),
+            c if c.is_ascii() => write!(out, "\\x{:02x}", c as u32),
+            c => write!(out, "\\u{{{:x}}}", c as u32),
+        };
+    };
+
+    match style {
+        QuoteStyle::Literal => path.into(),
+        QuoteStyle::C => {
+            if !path.contains(char::is_control) {
+                return path.into();
+            }
+            let mut out = String::with_capacity(path.len() + 8);
+            for c in path.chars() {
+                if c.is_control() {
+                    escape(c, &mut out);
+                } else {
+                    out.push(c);
+                }
+            }
+            out.into()
+        }
+        QuoteStyle::Shell => {
+            let safe = |c: char| c.is_alphanumeric() || "/._-+,:@%".contains(c);
+            if !path.is_empty() && path.chars().all(safe) {
+                return path.into();
+            }
+            // Control characters can only be written escaped, inside $'...'
+            if path.contains(char::is_control) {
+                let mut out = String::from("$'");
+                for c in path.chars() {
+                    match c {
+                        '\'' | '\\' => {
+                            out.push('\\');
+                            out.push(c);
+                        }
+                        c if c.is_control() => escape(c, &mut out),
+                        c => out.push(c),
+                    }
+                }
+                out.push('\'');
+                return out.into();
+            }
+            format!("'{}'", path.replace('\'', "'\\''")).into()
+        }
+    }
+}
+
+/// Removes all the `.` and `..` components of `path` without accessing the filesystem.
+pub fn normalize(path: &std::path::Path) -> std::path::PathBuf {
+    use std::path::Component;
+
+    let mut normalized = std::path::PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
 }
 
-pub fn select((ex, co): Buffers, mut stdout: impl std::io::Write) -> std::io::Result<()> {
-    let v = ex.into_iter().chain(co).collect();
-    let selected = inquire::Select::new("Select a file:", v).prompt();
+/// Returns `path` relative to `base`, adding `..` components where needed.
+///
+/// Both paths must be absolute and normalized.
+/// Returns `None` if `path` can't be expressed relative to `base` (e.g. they are in different drives on Windows).
+pub fn make_relative(base: &std::path::Path, path: &std::path::Path) -> Option<std::path::PathBuf> {
+    use std::path::Component;
+
+    let mut base = base.components().peekable();
+    let mut path = path.components().peekable();
+    if let (Some(Component::Prefix(b)), Some(Component::Prefix(p))) = (base.peek(), path.peek()) {
+        if b != p {
+            return None;
+        }
+    }
+    while matches!((base.peek(), path.peek()), (Some(b), Some(p)) if b == p) {
+        base.next();
+        path.next();
+    }
+    let relative: std::path::PathBuf = base.map(|_| Component::ParentDir).chain(path).collect();
+    if relative.as_os_str().is_empty() {
+        Some(".".into())
+    } else {
+        Some(relative)
+    }
+}
+
+/// Number of options rendered at once by the select interfaces, so big result sets don't lag.
+const PAGE_SIZE: usize = 15;
+
+pub fn select(
+    (ex, co, si): Buffers,
+    mut stdout: impl std::io::Write,
+    search: &Search,
+) -> std::io::Result<()> {
+    let v = ex.into_iter().chain(co).chain(si).collect();
+    let mut select = inquire::Select::new("Select a file:", v).with_page_size(PAGE_SIZE);
+    if let Some(filter) = &search.select_filter {
+        select = select.with_starting_filter_input(filter);
+    }
+    let selected = select.prompt();
     if let Ok(selected) = selected {
-        write!(stdout, "{selected}")?;
+        write!(stdout, "{}", search.display(&selected))?;
     }
     Ok(())
 }
 
-pub fn multiselect((ex, co): Buffers, mut stdout: impl std::io::Write) -> std::io::Result<()> {
-    let v = ex.into_iter().chain(co).collect();
-    let mut selected = inquire::MultiSelect::new("Select files:", v)
-        .prompt()
-        .unwrap_or_default()
-        .into_iter();
+pub fn multiselect(
+    (ex, co, si): Buffers,
+    mut stdout: impl std::io::Write,
+    search: &Search,
+) -> std::io::Result<()> {
+    let v = ex.into_iter().chain(co).chain(si).collect();
+    let mut multiselect = inquire::MultiSelect::new("Select files:", v).with_page_size(PAGE_SIZE);
+    if let Some(filter) = &search.select_filter {
+        multiselect = multiselect.with_starting_filter_input(filter);
+    }
+    let mut selected = multiselect.prompt().unwrap_or_default().into_iter();
 
     if let Some(f) = selected.next() {
-        write!(stdout, "{f}")?;
+        write!(stdout, "{}", search.display(&f))?;
     }
     for f in selected {
-        write!(stdout, " {f}")?;
+        write!(stdout, " {}", search.display(&f))?;
     }
     Ok(())
 }
@@ -83,40 +735,33 @@ pub fn print_with_highlight(
     sname: &str,
     path: &std::path::Path,
     search: &Search,
+    ls_style: Option<&str>,
 ) -> std::io::Result<()> {
     let ancestors = path.parent().unwrap();
 
-    let get_start_end = |s: &str| {
-        let start = sname.find(s).unwrap();
-        (start, start + s.len())
-    };
+    let starts = starts_len(sname, search);
+    let ends = ends_len(sname, search);
+    let [starts_idx, name_idx, ends_idx] =
+        highlight_spans(sname.len(), starts, match_span(sname, search), ends);
 
-    let starts_idx = if search.starts.is_empty() {
-        (0, 0)
-    } else {
-        get_start_end(&search.starts)
-    };
-    let name_idx = if search.name.is_empty() {
-        (starts_idx.1, starts_idx.1)
-    } else {
-        get_start_end(&search.name)
-    };
-    let ends_idx = if search.ends.is_empty() {
-        (name_idx.1, name_idx.1)
-    } else {
-        get_start_end(&search.ends)
-    };
-
-    use colored::Colorize;
+    use crate::lscolors::Painted;
 
     // let ancestors = ancestors.display();
-    let sep = std::path::MAIN_SEPARATOR;
-    let starts = &fname[starts_idx.0..starts_idx.1].bright_magenta().bold();
-    let starts_to_name = &fname[starts_idx.1..name_idx.0];
-    let name = &fname[name_idx.0..name_idx.1].bright_red().bold();
-    let name_to_ends = &fname[name_idx.1..ends_idx.0];
-    let ends = &fname[ends_idx.0..ends_idx.1].bright_magenta().bold();
-    let empty_ends = &fname[ends_idx.1..]; // Needed because we don't want to highlight the end of the path if "--ends" is not specified
+    let sep = separator_before_name(ancestors);
+    // Parts without a highlight keep the style of the rest of the name.
+    // The codes are written directly, as `colored` only follows the global color setting and not `search.colored`
+    let paint = |part: &str, highlight: Highlight| match highlight {
+        _ if part.is_empty() || !search.colored => Painted(ls_style, part).to_string(),
+        Highlight::Color(color) => format!("\x1b[1;{}m{part}\x1b[0m", color.to_fg_str()),
+        Highlight::Bold => format!("\x1b[1m{part}\x1b[0m"),
+        Highlight::None => Painted(ls_style, part).to_string(),
+    };
+    let starts = paint(&fname[starts_idx.0..starts_idx.1], search.affix_color);
+    let starts_to_name = Painted(ls_style, &fname[starts_idx.1..name_idx.0]);
+    let name = paint(&fname[name_idx.0..name_idx.1], search.name_color);
+    let name_to_ends = Painted(ls_style, &fname[name_idx.1..ends_idx.0]);
+    let ends = paint(&fname[ends_idx.0..ends_idx.1], search.affix_color);
+    let empty_ends = Painted(ls_style, &fname[ends_idx.1..]); // Needed because we don't want to highlight the end of the path if "--ends" is not specified
 
     if ancestors.as_os_str().len() > 1 || !ancestors.starts_with(std::path::MAIN_SEPARATOR_STR) {
         write!(stdout, "{}", ancestors.display())?;
@@ -127,14 +772,633 @@ pub fn print_with_highlight(
     )
 }
 
+/// Separator printed between `ancestors` and the file name.
+///
+/// Bare file names have no ancestors, like the results in the `--relative-to` directory.
+fn separator_before_name(ancestors: &std::path::Path) -> &'static str {
+    if ancestors.as_os_str().is_empty() {
+        ""
+    } else {
+        std::path::MAIN_SEPARATOR_STR
+    }
+}
+
+/// Byte ranges of the highlighted start, query and end of a file name of `len` bytes, in this order.
+///
+/// The affixes can overlap the query (e.g. '--starts fo' with the query "oo" in "foo"), so they are cut where the query starts and ends.
+/// Without a query span, the query is the empty range right after the start.
+fn highlight_spans(
+    len: usize,
+    starts: Option<usize>,
+    name: Option<(usize, usize)>,
+    ends: Option<usize>,
+) -> [(usize, usize); 3] {
+    let starts_end = starts.unwrap_or(0).min(len);
+    let (name_start, name_end) = name.unwrap_or((starts_end, starts_end));
+    let ends = match ends {
+        Some(ends) => (len.saturating_sub(ends).max(name_end), len),
+        None => (name_end, name_end),
+    };
+    let starts = (0, starts_end.min(name_start));
+    [starts, (name_start, name_end), ends]
+}
+
+/// Returns the byte range of the query in the file name `sname`, the part highlighted as the match.
+///
+/// Without a query, it's the empty range right after the longest matching '--starts'.
+/// `None` if the name doesn't contain the query.
+pub fn match_span(sname: &str, search: &Search) -> Option<(usize, usize)> {
+    if search.name.is_empty() {
+        let start = starts_len(sname, search).unwrap_or(0);
+        return Some((start, start));
+    }
+    // With '--anchored end' the query may also appear before the end of the name
+    let start = if search.anchored == Some(Anchor::End) {
+        sname.rfind(&search.name)
+    } else {
+        sname.find(&search.name)
+    };
+    start.map(|start| (start, start + search.name.len()))
+}
+
+/// Length of the start of the file name matched by `--starts` or `--starts-regex`, the longest if both match.
+fn starts_len(sname: &str, search: &Search) -> Option<usize> {
+    let literal = longest_affix(sname, &search.starts, |s, a| s.starts_with(a));
+    let regex = search.starts_regex.as_ref().and_then(|r| r.find(sname));
+    literal.max(regex.map(|m| m.end()))
+}
+
+/// Length of the end of the file name matched by `--ends` or `--ends-regex`, the longest if both match.
+fn ends_len(sname: &str, search: &Search) -> Option<usize> {
+    let literal = longest_affix(sname, &search.ends, |s, e| s.ends_with(e));
+    let regex = search.ends_regex.as_ref().and_then(|r| r.find(sname));
+    literal.max(regex.map(|m| sname.len() - m.start()))
+}
+
+/// Length of the longest of `affixes` that `matches` the file name, if multiple prefixes/suffixes match the longest one is highlighted.
+fn longest_affix(
+    sname: &str,
+    affixes: &[String],
+    matches: fn(&str, &str) -> bool,
+) -> Option<usize> {
+    affixes
+        .iter()
+        .filter(|a| matches(sname, a))
+        .map(String::len)
+        .max()
+}
+
 #[profi::profile]
 pub fn format_with_highlight(
     fname: &str,
     sname: &str,
     path: &std::path::Path,
     search: &Search,
+    ls_style: Option<&str>,
 ) -> String {
     let mut buffer = Vec::new();
-    print_with_highlight(&mut buffer, fname, sname, path, search).unwrap();
+    print_with_highlight(&mut buffer, fname, sname, path, search, ls_style).unwrap();
     unsafe { String::from_utf8_unchecked(buffer) }
 }
+
+/// Formats `path` with its file name painted with `ls_style`, without any match highlighting.
+pub fn format_with_ls_style(fname: &str, path: &std::path::Path, ls_style: Option<&str>) -> String {
+    let ancestors = path.parent().unwrap();
+    let sep = separator_before_name(ancestors);
+    let fname = crate::lscolors::Painted(ls_style, fname);
+    if ancestors.as_os_str().len() > 1 || !ancestors.starts_with(std::path::MAIN_SEPARATOR_STR) {
+        format!("{}{sep}{fname}", ancestors.display())
+    } else {
+        format!("{sep}{fname}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::{strip_ansi, TestDir};
+
+    /// Sorts the `(path, is_dir, depth)` entries in the order the `search` prints them.
+    fn sorted(search: &Search, entries: &[(&str, bool, usize)]) -> Vec<String> {
+        let entries = entries.iter().map(|&(path, is_dir, depth)| {
+            let mut entry = Entry::new(path.to_owned(), is_dir);
+            entry.depth = depth;
+            entry
+        });
+        let mut entries = entries.collect::<Vec<_>>();
+        entries.sort_by(|a, b| search.compare(a, b));
+        entries.iter().map(|e| e.path.to_string()).collect()
+    }
+
+    #[test]
+    fn directories_are_grouped() {
+        let entries = [
+            ("./b", false, 1),
+            ("./a/c", true, 2),
+            ("./a.rs", false, 1),
+            ("./a", true, 1),
+        ];
+        let search = Search::from_args(&["--dirs-first", "a"]);
+        assert_eq!(sorted(&search, &entries), ["./a", "./a/c", "./a.rs", "./b"]);
+        let search = Search::from_args(&["--files-first", "a"]);
+        assert_eq!(sorted(&search, &entries), ["./a.rs", "./b", "./a", "./a/c"]);
+        let search = Search::from_args(&["a"]);
+        assert_eq!(sorted(&search, &entries), ["./a", "./a.rs", "./a/c", "./b"]);
+    }
+
+    #[test]
+    fn directories_are_grouped_before_the_sort_key() {
+        let entries = [
+            ("./z", true, 1),
+            ("./a/b/c", false, 3),
+            ("./x/y", true, 2),
+            ("./a", false, 1),
+        ];
+        let search = Search::from_args(&["--dirs-first", "--sort", "depth", "a"]);
+        assert_eq!(
+            sorted(&search, &entries),
+            ["./z", "./x/y", "./a", "./a/b/c"]
+        );
+        let search = Search::from_args(&["--dirs-first", "--sort", "depth", "-R", "a"]);
+        assert_eq!(
+            sorted(&search, &entries),
+            ["./x/y", "./z", "./a/b/c", "./a"]
+        );
+    }
+
+    #[test]
+    fn collapse_prints_a_single_sorted_list() {
+        let dir = TestDir::new("collapse", &["b", "x/b", "a-b", "z-b", "y/b-c"]);
+        let output = dir.output_file();
+        let search = Search::from_args(&["--output-file", &output, "b", dir.arg()]);
+        let printed = dir.printed(&search);
+        let separate = ["Contains:", "a-b", "y/b-c", "z-b", "", "Exact:", "b", "x/b"];
+        assert_eq!(printed, separate);
+
+        let args = ["--collapse", "--output-file", &output, "b", dir.arg()];
+        let printed = dir.printed(&Search::from_args(&args));
+        assert_eq!(printed, ["a-b", "b", "x/b", "y/b-c", "z-b"]);
+        let mut sorted = printed.clone();
+        sorted.sort_unstable();
+        assert_eq!(printed, sorted);
+    }
+
+    #[test]
+    fn c_quoting_escapes_control_characters() {
+        let quoted = quote(QuoteStyle::C, "./new\nline\ttab");
+        assert_eq!(quoted, r"./new\nline\ttab");
+        assert_eq!(quote(QuoteStyle::C, "./\x1b[31mred"), r"./\x1b[31mred");
+        assert_eq!(
+            quote(QuoteStyle::C, "./next\u{85}line"),
+            r"./next\u{85}line"
+        );
+        let plain = quote(QuoteStyle::C, "./a b's");
+        assert!(matches!(plain, std::borrow::Cow::Borrowed("./a b's")));
+    }
+
+    #[test]
+    fn shell_and_literal_quoting() {
+        assert_eq!(
+            quote(QuoteStyle::Shell, "./new\nline\ttab"),
+            r"$'./new\nline\ttab'"
+        );
+        assert_eq!(quote(QuoteStyle::Shell, "./it's"), r"'./it'\''s'");
+        assert_eq!(quote(QuoteStyle::Shell, "./a b"), "'./a b'");
+        assert_eq!(
+            quote(QuoteStyle::Shell, "./plain-name.rs"),
+            "./plain-name.rs"
+        );
+        assert_eq!(quote(QuoteStyle::Literal, "./new\nline"), "./new\nline");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn c_quoting_of_a_file_name_with_a_newline_and_a_tab() {
+        let dir = TestDir::new("quote-style-c", &["new\nline", "a\ttab"]);
+        let output = dir.output_file();
+        let args = [
+            "--quote-style",
+            "c",
+            "--output-file",
+            &output,
+            "-s",
+            "",
+            dir.arg(),
+        ];
+        let printed = dir.printed(&Search::from_args(&args));
+        assert_eq!(printed, [r"a\ttab", r"new\nline"]);
+    }
+
+    #[test]
+    fn only_prints_the_chosen_matches() {
+        let dir = TestDir::new("only", &["b", "x/b", "a-b", "y/b-c"]);
+        let output = dir.output_file();
+        let args = |only| ["--only", only, "--output-file", &output, "b", dir.arg()];
+        let exact = dir.printed(&Search::from_args(&args("exact")));
+        assert_eq!(exact, ["b", "x/b"]);
+        let contains = dir.printed(&Search::from_args(&args("contains")));
+        assert_eq!(contains, ["a-b", "y/b-c"]);
+
+        let search = Search::from_args(&[&["--summary-json"][..], &args("contains")].concat());
+        let printed = dir.printed(&search).concat();
+        assert!(printed.contains(r#""kind":"contains""#), "{printed}");
+        assert!(!printed.contains(r#""kind":"exact""#), "{printed}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn type_tags_of_a_file_a_directory_and_a_symlink() {
+        let dir = TestDir::new("show-type", &["a-file", "a-dir/"]);
+        std::os::unix::fs::symlink(dir.join("a-file"), dir.join("a-link")).unwrap();
+        let output = dir.output_file();
+        let args = [
+            "--show-type",
+            "--output-file",
+            &output,
+            "-s",
+            "a-",
+            dir.arg(),
+        ];
+        let printed = dir.printed(&Search::from_args(&args));
+        assert_eq!(printed, ["d a-dir", "f a-file", "l a-link"]);
+    }
+
+    #[test]
+    fn deterministic_output_is_identical_between_runs() {
+        let entries = ["d/f1", "a/f2", "c/b/f3", "b/f4", "f5", "e/f6/", "e/f7"];
+        let dir = TestDir::new("deterministic", &entries);
+        let output = dir.output_file();
+        let args = [
+            "--deterministic",
+            "-ss",
+            "--output-file",
+            &output,
+            "f",
+            dir.arg(),
+        ];
+        let first = dir.printed(&Search::from_args(&args));
+        let second = dir.printed(&Search::from_args(&args));
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            ["a/f2", "b/f4", "c/b/f3", "d/f1", "e/f6", "e/f7", "f5"]
+        );
+
+        // Without it, -ss results are only printed while searching
+        let args = ["-ss", "--output-file", &output, "f", dir.arg()];
+        assert!(dir.printed(&Search::from_args(&args)).is_empty());
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = ["file20", "file1", "file10", "file2"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["file1", "file2", "file10", "file20"]);
+        let mut paths = ["a10/b2", "a2/b10", "a2/b9", "a10/b1"];
+        paths.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(paths, ["a2/b9", "a2/b10", "a10/b1", "a10/b2"]);
+
+        use std::cmp::Ordering;
+        assert_eq!(natural_cmp("file01", "file1"), Ordering::Equal);
+        assert_eq!(natural_cmp("file", "file1"), Ordering::Less);
+        assert_eq!(natural_cmp("file1a", "file1b"), Ordering::Less);
+        assert_eq!(natural_cmp("file99", "file100"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_sort_of_the_results() {
+        let entries = [
+            ("./file10", false, 1),
+            ("./file2", false, 1),
+            ("./file02", false, 1),
+            ("./file1", false, 1),
+            ("./file20", false, 1),
+        ];
+        let search = Search::from_args(&["--natural-sort", "file"]);
+        let natural = ["./file1", "./file02", "./file2", "./file10", "./file20"];
+        assert_eq!(sorted(&search, &entries), natural);
+        // Plain lexicographic order is still the default
+        let search = Search::from_args(&["file"]);
+        let lexicographic = ["./file02", "./file1", "./file10", "./file2", "./file20"];
+        assert_eq!(sorted(&search, &entries), lexicographic);
+    }
+
+    #[test]
+    fn highlight_spans_without_overlap() {
+        // "pre-foo.rs" with '--starts pre-', the query "foo" and '--ends .rs'
+        let spans = highlight_spans(10, Some(4), Some((4, 7)), Some(3));
+        assert_eq!(spans, [(0, 4), (4, 7), (7, 10)]);
+    }
+
+    #[test]
+    fn highlight_spans_cut_overlapping_affixes() {
+        // "foo" with '--starts fo' and the query "oo"
+        let spans = highlight_spans(3, Some(2), Some((1, 3)), None);
+        assert_eq!(spans, [(0, 1), (1, 3), (3, 3)]);
+        // "foo" with the query "fo" and '--ends oo'
+        let spans = highlight_spans(3, None, Some((0, 2)), Some(2));
+        assert_eq!(spans, [(0, 0), (0, 2), (2, 3)]);
+        // "foo" without a query, '--starts fo' and '--ends oo'
+        let spans = highlight_spans(3, Some(2), None, Some(2));
+        assert_eq!(spans, [(0, 2), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn highlight_spans_are_ordered() {
+        for len in 0..6 {
+            for starts in (0..=len).map(Some).chain([None]) {
+                for ends in (0..=len).map(Some).chain([None]) {
+                    for start in 0..=len {
+                        for end in start..=len {
+                            let spans = highlight_spans(len, starts, Some((start, end)), ends);
+                            let bounds = spans.iter().flat_map(|&(s, e)| [s, e]);
+                            let bounds = bounds.collect::<Vec<_>>();
+                            assert!(bounds.windows(2).all(|w| w[0] <= w[1]), "{bounds:?}");
+                            assert!(bounds.iter().all(|&b| b <= len), "{bounds:?}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn longest_affix_is_highlighted() {
+        let affixes = ["f".to_owned(), "foo".to_owned(), "fo".to_owned()];
+        let starts = |s: &str, a: &str| s.starts_with(a);
+        assert_eq!(longest_affix("foobar", &affixes, starts), Some(3));
+        assert_eq!(longest_affix("fob", &affixes, starts), Some(2));
+        assert_eq!(longest_affix("bar", &affixes, starts), None);
+
+        let ends = ["-test".to_owned(), "test".to_owned()];
+        let suffix = |s: &str, e: &str| s.ends_with(e);
+        assert_eq!(longest_affix("lib-test", &ends, suffix), Some(5));
+        assert_eq!(longest_affix("libtest", &ends, suffix), Some(4));
+        assert_eq!(longest_affix("lib", &[], suffix), None);
+    }
+
+    #[test]
+    fn trim_keeps_short_paths() {
+        assert_eq!(trim("./src/main.rs", 20, '/'), "./src/main.rs");
+        assert_eq!(trim("./src/main.rs", 13, '/'), "./src/main.rs");
+    }
+
+    #[test]
+    fn trim_long_paths() {
+        let path = "/home/user/projects/hunt/src/main.rs";
+        assert_eq!(trim(path, 20, '/'), "/home/use.../main.rs");
+        let dir = "/home/user/projects/hunt/src/";
+        assert_eq!(trim(dir, 15, '/'), "/home/u.../src/");
+        // Characters are counted, not bytes
+        assert_eq!(trim("/ñañaña/ñañaña/ñ.rs", 10, '/'), "/ñ.../ñ.rs");
+        let windows = r"C:\Users\user\Documents\a.txt";
+        assert_eq!(trim(windows, 16, '\\'), r"C:\User...\a.txt");
+    }
+
+    #[test]
+    fn trim_keeps_the_whole_file_name() {
+        let path = "/home/user/a-very-long-file-name.txt";
+        assert_eq!(trim(path, 12, '/'), ".../a-very-long-file-name.txt");
+        // Replacing the top wouldn't make it shorter
+        let path = "/a/a-very-long-file-name.txt";
+        assert_eq!(trim(path, 12, '/'), path);
+    }
+
+    #[test]
+    fn trim_keeps_the_colors() {
+        let path = "\x1b[1m/home/user/projects/hunt/src/\x1b[0mmain.rs";
+        let trimmed = trim(path, 20, '/');
+        assert_eq!(trimmed, "\x1b[1m/home/use.../\x1b[0mmain.rs");
+        assert_eq!(strip_ansi(&trimmed), "/home/use.../main.rs");
+    }
+
+    #[test]
+    fn make_relative_to_nested_and_sibling_bases() {
+        use std::path::{Path, PathBuf};
+
+        let relative = |base, path| make_relative(Path::new(base), Path::new(path));
+        let path = |path: &str| Some(PathBuf::from(path));
+        assert_eq!(relative("/home/user", "/home/user/docs/a"), path("docs/a"));
+        let nested = relative("/home/user/docs/notes", "/home/user/docs/a");
+        assert_eq!(nested, path("../a"));
+        let sibling = relative("/home/user/Downloads", "/home/user/Documents/file");
+        assert_eq!(sibling, path("../Documents/file"));
+        assert_eq!(relative("/home/user/a/b", "/home"), path("../../.."));
+        assert_eq!(relative("/home/user", "/home/user"), path("."));
+        assert_eq!(relative("/", "/etc/hosts"), path("etc/hosts"));
+    }
+
+    #[test]
+    fn normalize_removes_dots() {
+        let normalized = normalize(std::path::Path::new("/home/./user/../other/a"));
+        assert_eq!(normalized, std::path::Path::new("/home/other/a"));
+    }
+
+    #[test]
+    fn bare_file_names_are_highlighted_without_separator() {
+        let search = Search::from_args(&["mai"]);
+        let path = std::path::Path::new("main.rs");
+        let line = format_with_highlight("main.rs", "main.rs", path, &search, None);
+        assert_eq!(strip_ansi(&line), "main.rs");
+        let line = format_with_ls_style("main.rs", path, None);
+        assert_eq!(line, "main.rs");
+    }
+
+    #[test]
+    fn format_size_units() {
+        let binary = |bytes| format_size(bytes, SizeUnits::Binary);
+        let decimal = |bytes| format_size(bytes, SizeUnits::Decimal);
+        assert_eq!(binary(0), "0 B");
+        assert_eq!(decimal(0), "0 B");
+        assert_eq!(binary(1023), "1023 B");
+        assert_eq!(binary(1024), "1.0 KiB");
+        assert_eq!(decimal(1024), "1.0 kB");
+        assert_eq!(binary(1536), "1.5 KiB");
+        assert_eq!(decimal(1536), "1.5 kB");
+        assert_eq!(decimal(999), "999 B");
+        assert_eq!(decimal(1000), "1.0 kB");
+    }
+
+    #[test]
+    fn format_size_rounds_up_to_the_next_unit() {
+        assert_eq!(format_size(1024 * 1024 - 1, SizeUnits::Binary), "1.0 MiB");
+        assert_eq!(format_size(1023 * 1024, SizeUnits::Binary), "1023.0 KiB");
+        assert_eq!(format_size(999_960, SizeUnits::Decimal), "1.0 MB");
+        assert_eq!(format_size(999_940, SizeUnits::Decimal), "999.9 kB");
+        assert_eq!(format_size(u64::MAX, SizeUnits::Binary), "16384.0 PiB");
+    }
+
+    #[test]
+    fn ends_regex_with_alternation() {
+        let search = Search::from_args(&["--ends-regex", r"\.(jpe?g|png)"]);
+        assert_eq!(ends_len("photo.jpg", &search), Some(4));
+        assert_eq!(ends_len("photo.jpeg", &search), Some(5));
+        assert_eq!(ends_len("photo.png", &search), Some(4));
+        assert_eq!(ends_len("photo.gif", &search), None);
+        assert_eq!(ends_len("photo.png.gif", &search), None);
+
+        let search = Search::from_args(&["--ends-regex", r"\.(jpe?g|png)$"]);
+        assert_eq!(ends_len("photo.JPEG", &search), Some(5));
+    }
+
+    #[test]
+    fn regex_affix_overlapping_the_query() {
+        let search = Search::from_args(&["--starts-regex", "f.", "oo"]);
+        let path = std::path::Path::new("./foo");
+        let line = format_with_highlight("foo", "foo", path, &search, None);
+        assert_eq!(strip_ansi(&line), "./foo");
+
+        let search = Search::from_args(&["--ends-regex", "o+", "fo"]);
+        let line = format_with_highlight("foo", "foo", path, &search, None);
+        assert_eq!(strip_ansi(&line), "./foo");
+    }
+
+    /// Search with the `args`, printing colors even if stdout is not a terminal.
+    fn colored_search(args: &[&str]) -> Search {
+        let mut search = Search::from_args(args);
+        search.colored = true;
+        search
+    }
+
+    #[test]
+    fn highlight_colors() {
+        let path = std::path::Path::new("./xmain.rs");
+        let line = |args: &[&str]| {
+            let search = colored_search(&[args, &["--starts", "x", "main"]].concat());
+            format_with_highlight("xmain.rs", "xmain.rs", path, &search, None)
+        };
+        let args = ["--color-name", "blue", "--color-affix", "none"];
+        assert_eq!(line(&args), "./x\x1b[1;34mmain\x1b[0m.rs");
+        let args = ["--color-affix", "bright-green"];
+        assert_eq!(line(&args), "./\x1b[1;92mx\x1b[0m\x1b[1;91mmain\x1b[0m.rs");
+        // Nothing is highlighted in an '--output-file', whatever the colors
+        let dir = TestDir::new("highlight-colors", &[]);
+        let output = dir.output_file();
+        let args = [
+            "--output-file",
+            &output,
+            "--color-name",
+            "blue",
+            "--starts",
+            "x",
+            "main",
+        ];
+        let search = Search::from_args(&args);
+        assert!(!search.colored);
+        let line = format_with_highlight("xmain.rs", "xmain.rs", path, &search, None);
+        assert_eq!(line, "./xmain.rs");
+    }
+
+    #[test]
+    fn relative_roots_strip_the_root_of_each_result() {
+        let entries = ["a/", "a/x.txt", "b/", "b/sub/", "b/sub/x.txt"];
+        let dir = TestDir::new("relative-roots", &entries);
+        let output = dir.output_file();
+        let printed = |roots: [&str; 2]| {
+            let (first, second) = (dir.join(roots[0]), dir.join(roots[1]));
+            let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+            let args = [
+                "--relative-roots",
+                "--only",
+                "contains",
+                "--output-file",
+                &output,
+                "x",
+                first,
+                second,
+            ];
+            dir.printed(&Search::from_args(&args))
+        };
+        assert_eq!(printed(["a", "b"]), ["x.txt", "sub/x.txt"]);
+        // A result inside both roots is found from each of them, relative to the root that found it
+        let mut nested = printed(["b", "b/sub"]);
+        nested.sort();
+        assert_eq!(nested, ["sub/x.txt", "x.txt"]);
+    }
+
+    #[test]
+    fn sort_by_time() {
+        let times = [
+            ("./a", Some(30)),
+            ("./b", Some(10)),
+            ("./c", None),
+            ("./d", Some(10)),
+        ];
+        let sorted = |args: &[&str]| {
+            let search = Search::from_args(&[args, &["x"]].concat());
+            let results = times.iter().map(|&(path, secs)| {
+                let time = secs.map(|s| std::time::UNIX_EPOCH + std::time::Duration::from_secs(s));
+                crate::searchresult::SearchResult::contains(path.to_owned(), false)
+                    .with_sort_time(time)
+            });
+            let mut results = results.collect::<Vec<_>>();
+            results.sort_by(|a, b| search.compare(a.entry(), b.entry()));
+            let paths = results.iter().map(|r| r.entry().path.to_string());
+            paths.collect::<Vec<_>>()
+        };
+        for key in ["accessed", "created"] {
+            assert_eq!(sorted(&["--sort", key]), ["./b", "./d", "./a", "./c"]);
+            // Results with the same time are still sorted by path, and the ones without a time are still last
+            assert_eq!(sorted(&["--sort", key, "-R"]), ["./a", "./b", "./d", "./c"]);
+        }
+    }
+
+    #[test]
+    fn disk_usage_is_added_up_to_the_root() {
+        let dir = TestDir::new("du", &["a/b/", "c/"]);
+        let sizes = [
+            ("a/x.mp4", 2000),
+            ("a/b/y.mp4", 1000),
+            ("c/z.mp4", 500),
+            ("c/big.txt", 9000),
+        ];
+        for (file, size) in sizes {
+            let file = std::fs::File::create(dir.join(file)).unwrap();
+            file.set_len(size).unwrap();
+        }
+        let output = dir.output_file();
+        let printed = |units| {
+            let args = [
+                "--du",
+                "--size-units",
+                units,
+                "--ends",
+                ".mp4",
+                "--output-file",
+                &output,
+                "",
+                dir.arg(),
+            ];
+            let printed = dir.printed(&Search::from_args(&args));
+            // Only the prefix with the separator is removed by `printed`, so the root keeps its path
+            let root = dir.arg().replace(std::path::MAIN_SEPARATOR, "/");
+            let printed = printed.iter().map(|line| line.replace(&root, "."));
+            printed.collect::<Vec<_>>()
+        };
+        let binary = ["3.4 KiB\t.", "2.9 KiB\ta", "1000 B\ta/b", "500 B\tc"];
+        assert_eq!(printed("binary"), binary);
+        let decimal = ["3.5 kB\t.", "3.0 kB\ta", "1.0 kB\ta/b", "500 B\tc"];
+        assert_eq!(printed("decimal"), decimal);
+    }
+
+    #[test]
+    fn color_themes() {
+        let path = std::path::Path::new("./xmain.rs");
+        let line = |args: &[&str]| {
+            let search = colored_search(&[args, &["--starts", "x", "main"]].concat());
+            format_with_highlight("xmain.rs", "xmain.rs", path, &search, None)
+        };
+        let highlighted = |affix, name| format!("./{affix}x\x1b[0m{name}main\x1b[0m.rs");
+        let dark = highlighted("\x1b[1;95m", "\x1b[1;91m");
+        assert_eq!(line(&[]), dark);
+        assert_eq!(line(&["--theme", "dark"]), dark);
+        let light = highlighted("\x1b[1;34m", "\x1b[1;31m");
+        assert_eq!(line(&["--theme", "light"]), light);
+        // Mono has no colors, only bold
+        let mono = highlighted("\x1b[1m", "\x1b[1m");
+        assert_eq!(line(&["--theme", "mono"]), mono);
+        // The colors given explicitly replace the ones of the theme
+        let args = ["--theme", "mono", "--color-name", "green"];
+        assert_eq!(line(&args), highlighted("\x1b[1m", "\x1b[1;32m"));
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Counters shared by all the search threads, reported to stderr with `--progress`.
+#[derive(Default)]
+pub struct Progress {
+    dirs: AtomicUsize,
+    matches: AtomicUsize,
+    done: AtomicBool,
+}
+
+impl Progress {
+    /// Starts a thread that prints the counters to stderr a few times per second, until [`Progress::finish`] is called.
+    pub fn spawn(self: &Arc<Self>) {
+        let progress = Arc::clone(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            // Hold the lock while checking, so nothing is printed after the line is cleared
+            let mut stderr = std::io::stderr().lock();
+            if progress.done.load(Ordering::Relaxed) {
+                return;
+            }
+            let _ = write!(
+                stderr,
+                "\r\x1b[2Kscanned {} dirs, found {} matches",
+                progress.dirs.load(Ordering::Relaxed),
+                progress.matches.load(Ordering::Relaxed)
+            );
+        });
+    }
+
+    pub fn dir(&self) {
+        self.dirs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn found(&self) {
+        self.matches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Stops the reporter thread and clears its line.
+    pub fn finish(&self) {
+        let mut stderr = std::io::stderr().lock();
+        if !self.done.swap(true, Ordering::Relaxed) {
+            let _ = write!(stderr, "\r\x1b[2K");
+        }
+    }
+}
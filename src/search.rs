@@ -1,16 +1,49 @@
 use crate::{
+    candidate::{Candidate, PathCandidate},
     searchresult::SearchResult,
-    structs::{Buffers, FileType, Output, Search},
+    skipped::Skip,
+    structs::{Anchor, Buffers, FileType, Only, Output, QuoteStyle, Search, Sort},
 };
-use std::path::Path;
+use std::{path::Path, sync::atomic::Ordering};
 
 type Receiver = crossbeam_channel::Receiver<SearchResult>;
-type Sender = crossbeam_channel::Sender<SearchResult>;
+/// Receives the results from the search threads, as soon as they are found.
+type Sink<'a> = &'a (dyn Fn(SearchResult) + Sync);
 
 impl Search {
     #[profi::profile]
     pub fn search(&self) -> Buffers {
         let (sender, receiver) = crossbeam_channel::bounded(8);
+        if let Some(progress) = &self.progress {
+            progress.spawn();
+        }
+        rayon::scope(|s| {
+            s.spawn(move |_| self.search_with_sink(&move |result| sender.send(result).unwrap()));
+            receive_paths(receiver, self)
+        })
+    }
+
+    /// Runs the search, calling `sink` with every result as soon as it's found, instead of collecting them.
+    ///
+    /// The results are unordered, and `sink` is called from multiple search threads at the same time.
+    /// Returns once the whole search is finished.
+    pub fn search_with_sink(&self, sink: Sink) {
+        self.followed.lock().unwrap().clear();
+        if let Some(unique) = &self.unique {
+            unique.lock().unwrap().clear();
+        }
+        if let Some(skipped) = &self.skipped {
+            skipped.clear();
+        }
+
+        // With --files0-from, only the listed paths are checked
+        if let Some(files) = &self.files {
+            use rayon::prelude::*;
+            files
+                .par_iter()
+                .for_each(|path| self.match_path(path, sink));
+            return;
+        }
 
         // If no limit, search current directory
         if !self.limit {
@@ -21,46 +54,65 @@ impl Search {
             } else {
                 std::borrow::Cow::Borrowed(std::path::Path::new("."))
             };
-            return rayon::scope(|s| {
-                s.spawn(|_| search_dir(path, self, sender, 0));
-                receive_paths(receiver, self)
-            });
+            let relative = |result: SearchResult| sink(result.with_root(&path));
+            let sink: Sink = if self.needs_root() { &relative } else { sink };
+            self.match_root(&path, sink);
+            return search_dir(&*path, self, sink, 0, usize::MAX);
         }
         // Check if paths are valid and canonicalize if necessary
-        let dirs = self.dirs.iter().map(|path| {
+        let dirs = self.dirs.iter().map(|(path, depth_limit)| {
             if !path.exists() {
                 eprintln!("Error: The {:?} directory does not exist", path);
                 std::process::exit(1)
             }
-            if self.canonicalize {
+            let path = if self.canonicalize {
                 std::borrow::Cow::<Path>::Owned(path.canonicalize().unwrap_or_else(|_| {
                     eprintln!("Error: The {:?} directory does not exist", path);
                     std::process::exit(1)
                 }))
             } else {
                 std::borrow::Cow::<Path>::Borrowed(path)
-            }
+            };
+            (path, depth_limit.unwrap_or(usize::MAX))
         });
 
         // Search in directories
         rayon::scope(move |s| {
-            for dir in dirs {
-                let sender = sender.clone();
-                s.spawn(move |_| search_dir(dir, self, sender, 0));
+            for (dir, depth_limit) in dirs {
+                s.spawn(move |_| {
+                    // Each root gets its own sink, so results are attributed to the search that found them
+                    let relative = |result: SearchResult| sink(result.with_root(&dir));
+                    let sink: Sink = if self.needs_root() { &relative } else { sink };
+                    self.match_root(&dir, sink);
+                    search_dir(&*dir, self, sink, 0, depth_limit)
+                });
             }
-            drop(sender);
-            receive_paths(receiver, self)
         })
     }
 }
 
+/// Searches the entries of `path`, which is `depth` levels below its search root.
+///
+/// Subdirectories are only searched if their entries are at most `depth_limit` levels below the root.
 #[profi::profile]
-fn search_dir(path: impl AsRef<Path>, search: &Search, sender: Sender, depth: usize) {
+fn search_dir(
+    path: impl AsRef<Path>,
+    search: &Search,
+    sink: Sink,
+    depth: usize,
+    depth_limit: usize,
+) {
+    if depth >= depth_limit {
+        return;
+    }
     let path = path.as_ref();
     
+    // Held until all the entries are checked, as they keep the directory open
+    let permit = search.open_permit(true);
     let read = {
         profi::prof!("search_dir::read_dir");
         let Ok(read) = std::fs::read_dir(path) else {
+            search.incomplete.store(true, Ordering::Relaxed);
             if search.verbose {
                 eprintln!("Could not read {:?}", path);
             }
@@ -68,33 +120,91 @@ fn search_dir(path: impl AsRef<Path>, search: &Search, sender: Sender, depth: us
         };
         read
     };
+    if let Some(scanned) = &search.scanned_dirs {
+        scanned.fetch_add(1, Ordering::Relaxed);
+    }
+    if let Some(progress) = &search.progress {
+        progress.dir();
+    }
 
-    rayon::scope(|s| {
+    // Called with a function that searches a subdirectory in parallel, with its depth limit
+    let inspect = |spawn: &mut dyn FnMut(Box<Path>, usize)| {
         profi::prof!("search_dir::inspect_entries");
+        let mut matched = false;
+        let mut siblings = Vec::new();
+        // Searched in this thread once this directory is closed, so it's never open while its subdirectories are read
+        let mut deep = Vec::new();
         for entry in read.flatten() {
             profi::prof!("search_dir::inspect_entry");
+            let sibling = search.siblings.then(|| entry.path());
             let Some((result, is_dir)) = is_result(entry, search) else {
                 continue;
             };
             if let Some(result) = result {
                 profi::prof!("search_dir::send_result");
-                sender.send(result).unwrap();
+                if let Some(progress) = &search.progress {
+                    progress.found();
+                }
+                sink(result.with_depth(depth + 1));
+                matched = true;
+            } else if let Some(sibling) = sibling {
+                siblings.push((sibling, is_dir.is_some()));
             }
-            if let Some(path) = is_dir {
+            let traverse = is_dir.map(|path| {
+                let limit = search.follow_limit(&path, depth + 1, depth_limit);
+                (path, limit)
+            });
+            let traverse = traverse.filter(|(p, limit)| depth + 1 < *limit && !search.skips(p));
+            if let Some((path, limit)) = traverse {
                 profi::prof!("search_dir::spawn_search_dir");
                 if depth > search.max_depth {
-                    search_dir(path, search, sender.clone(), depth);
+                    deep.push((path, limit));
                     continue;
                 }
-                s.spawn(|_| search_dir(path, search, sender.clone(), depth + 1));
+                spawn(path, limit);
+            }
+        }
+        drop(permit);
+        if matched {
+            profi::prof!("search_dir::send_siblings");
+            for (path, is_dir) in siblings {
+                let candidate = || PathCandidate::new(path.clone()).ok();
+                let inode = search.inode.then(|| candidate()?.inode()).flatten();
+                let type_tag = search
+                    .show_type
+                    .then(|| type_tag(candidate().map(|c| c.file_type())));
+                let git_status = search.git_status.as_ref().map(|g| g.of(&path));
+                let path = search.relative_path(&path).unwrap_or(path);
+                let path = path.to_string_lossy().into_owned();
+                let quoted = search.needs_quoting(&path);
+                let sibling = SearchResult::sibling(path, is_dir)
+                    .with_inode(inode)
+                    .with_type_tag(type_tag)
+                    .with_git_status(git_status)
+                    .with_quoting(quoted)
+                    .with_depth(depth + 1);
+                sink(sibling);
             }
         }
-    });
+        for (path, limit) in deep {
+            search_dir(path, search, sink, depth + 1, limit);
+        }
+    };
+
+    let search_subdir = move |path, limit| search_dir(path, search, sink, depth + 1, limit);
+    // With --shallow-first the subdirectories are searched in the order they are found, so shallower ones go first
+    if search.shallow_first {
+        rayon::scope_fifo(|s| {
+            inspect(&mut |path, limit| s.spawn_fifo(move |_| search_subdir(path, limit)))
+        });
+    } else {
+        rayon::scope(|s| inspect(&mut |path, limit| s.spawn(move |_| search_subdir(path, limit))));
+    }
 }
 
 #[profi::profile]
 fn is_result(
-    entry: std::fs::DirEntry,
+    entry: impl Candidate,
     search: &Search,
 ) -> Option<(Option<SearchResult>, Option<Box<Path>>)> {
     // Get entry name
@@ -103,7 +213,7 @@ fn is_result(
         entry.path()
     };
 
-    if !search.explicit_ignore.is_empty() {
+    if !search.explicit_ignore.is_empty() || search.explicit_ignore_globs.is_some() {
         profi::prof!("is_result::explicit_ignore");
         let canonicalized = path.canonicalize().ok()?;
         let ignore = |entry: &std::path::PathBuf| {
@@ -113,7 +223,20 @@ fn is_result(
                 entry.file_name() == path.file_name()
             }
         };
-        if search.explicit_ignore.iter().any(ignore) {
+        let globs = &search.explicit_ignore_globs;
+        if search.explicit_ignore.iter().any(ignore)
+            || globs.as_ref().is_some_and(|g| g.is_match(&canonicalized))
+        {
+            search.record_skip(&path, Skip::Ignored);
+            return None;
+        }
+    }
+
+    if let Some(ignore_file) = &search.ignore_file {
+        profi::prof!("is_result::ignore_file");
+        let is_dir = matches!(entry.file_type(), Ok(ftype) if ftype.is_dir());
+        if file_name(&path).is_some_and(|name| ignore_file.matches(&path, name, is_dir)) {
+            search.record_skip(&path, Skip::IgnoreFile);
             return None;
         }
     }
@@ -130,31 +253,41 @@ fn is_result(
         }
     };
 
-    {
+    // Hidden entries are skipped, unless they can be traversed or matched
+    let hidden = {
         profi::prof!("is_result::hidden_check");
-        if !search.hidden && is_hidden() {
-            return None;
-        }
+        !(search.traverse_hidden && search.match_hidden) && is_hidden()
+    };
+    if hidden && !search.traverse_hidden && !search.match_hidden {
+        search.record_skip(&path, Skip::Hidden);
+        return None;
     }
 
     // Read type of file and check if it should be added to search results
+    let is_symlink = matches!(entry.file_type(), Ok(ftype) if ftype.is_symlink());
+    // A symlink is never a directory, even on platforms that report the type of its target
     let is_dir = {
         profi::prof!("is_result::is_dir");
-        matches!(entry.file_type(), Ok(ftype) if ftype.is_dir())
+        !is_symlink && matches!(entry.file_type(), Ok(ftype) if ftype.is_dir())
     };
+    // Symlinks are only searched if they point inside one of the '--follow-under' directories
+    let traverse = (!hidden || search.traverse_hidden)
+        && (is_dir || (is_symlink && !search.follow_under.is_empty() && search.follows(&path)));
     let ftype = {
         profi::prof!("is_result::get_ftype");
 
-        match search.ftype {
+        let ftype = match search.ftype {
             FileType::All => true,
             FileType::Dir => is_dir,
             FileType::File => !is_dir,
-        }
+            FileType::Symlink => is_symlink,
+        };
+        ftype && (!hidden || search.match_hidden)
     };
 
     let Some(fname) = file_name(&path) else {
         profi::prof!("is_result::return_invalid_file_name");
-        return Some((None, is_dir.then_some(path.into_boxed_path())));
+        return Some((None, traverse.then_some(path.into_boxed_path())));
     };
     let fname = {
         profi::prof!("is_result::fname.to_string_lossy");
@@ -170,85 +303,666 @@ fn is_result(
 
     let starts = || {
         profi::prof!("is_result::starts_with");
-        search.starts.is_empty() || sname.starts_with(&search.starts)
+        let literal =
+            search.starts.is_empty() || search.starts.iter().any(|s| sname.starts_with(s));
+        literal && search.starts_regex.iter().all(|r| r.is_match(&*sname))
     };
     let ends = || {
         profi::prof!("is_result::ends_with");
-        search.ends.is_empty() || sname.ends_with(&search.ends)
+        let literal = search.ends.is_empty() || search.ends.iter().any(|e| sname.ends_with(e));
+        literal && search.ends_regex.iter().all(|r| r.is_match(&*sname))
     };
 
-    profi::prof!("is_result::substring_checks");
-    if ftype && starts() && ends() {
-        let (equals, contains) = {
-            profi::prof!("is_result::contains");
-            if search.finder.find(sname.as_bytes()).is_none() {
-                (false, false)
+    let parent = || {
+        profi::prof!("is_result::parent");
+        let Some(parent) = &search.parent else {
+            return true;
+        };
+        let name = path.parent().and_then(file_name);
+        name.is_some_and(|name| {
+            let name = name.to_string_lossy();
+            if search.case_sensitive {
+                name == parent.as_str()
             } else {
-                (sname.len() == search.name.len(), true)
+                name.eq_ignore_ascii_case(parent)
             }
+        })
+    };
+
+    let globbed = || {
+        profi::prof!("is_result::glob_from");
+        search.glob_from.as_ref().map_or(true, |globs| {
+            let is_dir = matches!(entry.file_type(), Ok(ftype) if ftype.is_dir());
+            file_name(&path).is_some_and(|name| globs.matches(&path, name, is_dir))
+        })
+    };
+
+    let excluded = || {
+        profi::prof!("is_result::exclude_ext");
+        !is_dir
+            && !search.exclude_ext.is_empty()
+            && path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| search.exclude_ext.iter().any(|x| x.eq_ignore_ascii_case(e)))
+    };
+
+    // The first filter that leaves the entry out, regardless of its name
+    let rejected = {
+        profi::prof!("is_result::substring_checks");
+        if !ftype {
+            let hidden = hidden && !search.match_hidden;
+            Some(if hidden { Skip::Hidden } else { Skip::Type })
+        } else if !starts() || !ends() {
+            Some(Skip::Affix)
+        } else if !parent() {
+            Some(Skip::Parent)
+        } else if !globbed() {
+            Some(Skip::Glob)
+        } else if excluded() {
+            Some(Skip::Extension)
+        } else {
+            None
+        }
+    };
+
+    let name_matches = || {
+        profi::prof!("is_result::contains");
+        let anchored = match search.anchored {
+            None => true,
+            Some(Anchor::Start) => sname.starts_with(search.name.as_str()),
+            Some(Anchor::End) => sname.ends_with(search.name.as_str()),
+            Some(Anchor::Both) => sname == search.name.as_str(),
+        };
+        let all = || {
+            let bytes = sname.as_bytes();
+            search.require_all.iter().all(|f| f.find(bytes).is_some())
         };
+        if !anchored || search.finder.find(sname.as_bytes()).is_none() || !all() {
+            (false, false)
+        } else if !search.require_all.is_empty() {
+            (false, true)
+        } else {
+            let equals = if search.exact_stem {
+                Path::new(sname.as_ref()).file_stem() == Some(search.name.as_ref())
+            } else {
+                sname.len() == search.name.len()
+            };
+            // With '--only contains' the exact matches are skipped
+            if search.only == Some(Only::Contains) {
+                (false, !equals)
+            } else {
+                (equals, true)
+            }
+        }
+    };
+
+    if rejected.is_none() {
+        let (equals, contains) = name_matches();
+        let matched = equals || (!search.exact && contains);
+        let filtered = || {
+            let duplicate = || (!search.is_first_link(&entry)).then_some(Skip::Duplicate);
+            search.metadata_skip(&entry).or_else(duplicate)
+        };
+        if let Some(reason) = matched.then(filtered).flatten() {
+            profi::prof!("is_result::return_filtered_metadata");
+            search.record_skip(&path, reason);
+            return Some((None, traverse.then_some(path.into_boxed_path())));
+        }
+        if let Some(total) = &search.total_size {
+            if !is_dir && (equals || (!search.exact && contains)) {
+                profi::prof!("is_result::total_size");
+                let len = entry.metadata().map_or(0, |m| m.len());
+                let _ = total.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |t| {
+                    Some(t.saturating_add(len))
+                });
+            }
+        }
+        let ls_style = || {
+            profi::prof!("is_result::ls_style");
+            search
+                .ls_colors
+                .as_ref()
+                .filter(|_| search.colored && search.output == Output::Normal)
+                .and_then(|c| c.style(&fname, &entry))
+        };
+        // With --resolve-output, matches are printed with their canonical path
+        let resolved = if search.resolve_output && (equals || (!search.exact && contains)) {
+            profi::prof!("is_result::resolve_output");
+            match path.canonicalize() {
+                Ok(resolved) => Some(resolved),
+                Err(e) => {
+                    if search.verbose {
+                        eprintln!("Could not canonicalize {:?}, printed as found: {e}", path);
+                    }
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let relative = search.relative_path(resolved.as_deref().unwrap_or(&path));
+        let out_path = relative.as_deref().or(resolved.as_deref()).unwrap_or(&path);
+        let inode = || search.inode.then(|| entry.inode()).flatten();
+        let size = || {
+            let metadata = (search.disk_usage && !is_dir).then(|| entry.metadata().ok());
+            metadata.flatten().map(|m| m.len())
+        };
+        let type_tag = || search.show_type.then(|| type_tag(Some(entry.file_type())));
+        let git_status = || search.git_status.as_ref().map(|g| g.of(&path));
+        let modified = || {
+            let by_mtime = search.newest.is_some() || search.oldest.is_some();
+            by_mtime
+                .then(|| entry.metadata().ok()?.modified().ok())
+                .flatten()
+        };
+        let quoted = search.needs_quoting(&out_path.to_string_lossy());
+        // Highlighting and offsets reuse the file name, which changes if a symlink was resolved
+        let same_name = (resolved.is_none() && relative.is_none())
+            || file_name(out_path).is_some_and(|n| n.to_string_lossy() == fname);
+        let show_offsets = search.show_offsets && same_name;
+        let match_span = || {
+            let span = || crate::print::match_span(&sname, search);
+            show_offsets.then(span).flatten()
+        };
+        // Highlighting is also skipped when the paths are processed afterwards, like filtering them in the select interfaces
+        let highlight = search.output == Output::Normal
+            && !search.summary_json
+            && !quoted
+            && !search.select
+            && !search.multiselect
+            && !search.count_by_extension
+            && !search.disk_usage
+            && !search.interactive
+            && same_name;
         // If file name is equal to search name, write it to the "Exact" buffer
         if equals {
             profi::prof!("is_result::return_exact");
+            let s = match ls_style().filter(|_| highlight) {
+                Some(style) => crate::print::format_with_ls_style(&fname, out_path, Some(style)),
+                None => out_path.to_string_lossy().into_owned(),
+            };
             return Some((
-                Some(SearchResult::exact(path.to_string_lossy().into_owned())),
-                is_dir.then_some(path.into_boxed_path()),
+                Some(
+                    SearchResult::exact(s, is_dir)
+                        .with_inode(inode())
+                        .with_type_tag(type_tag())
+                        .with_git_status(git_status())
+                        .with_modified(modified())
+                        .with_sort_time(search.sort_time(&entry))
+                        .with_size(size())
+                        .with_quoting(quoted)
+                        .with_match_span(match_span()),
+                ),
+                traverse.then_some(path.into_boxed_path()),
             ));
         }
         // If file name contains the search name, write it to the "Contains" buffer
         else if !search.exact && contains {
-            let s = if search.output == Output::Normal {
-                crate::print::format_with_highlight(&fname, &sname, &path, search)
+            let s = if highlight {
+                crate::print::format_with_highlight(&fname, &sname, out_path, search, ls_style())
             } else {
-                path.to_string_lossy().into_owned()
+                out_path.to_string_lossy().into_owned()
             };
             profi::prof!("is_result::return_contains");
             return Some((
-                Some(SearchResult::contains(s)),
-                is_dir.then_some(path.into_boxed_path()),
+                Some(
+                    SearchResult::contains(s, is_dir)
+                        .with_inode(inode())
+                        .with_type_tag(type_tag())
+                        .with_git_status(git_status())
+                        .with_modified(modified())
+                        .with_sort_time(search.sort_time(&entry))
+                        .with_size(size())
+                        .with_quoting(quoted)
+                        .with_match_span(match_span()),
+                ),
+                traverse.then_some(path.into_boxed_path()),
             ));
         }
     }
+    // Only the names that would have been found are reported, or every entry would be
+    if let Some(reason) = rejected.filter(|_| search.skipped.is_some()) {
+        let (equals, contains) = name_matches();
+        if equals || (!search.exact && contains) {
+            search.record_skip(&path, reason);
+        }
+    }
     profi::prof!("is_result::return_not_found");
-    Some((None, is_dir.then_some(path.into_boxed_path())))
+    Some((None, traverse.then_some(path.into_boxed_path())))
 }
 
 fn receive_paths(receiver: Receiver, search: &Search) -> Buffers {
     use std::io::Write;
 
+    let finish_progress = || {
+        if let Some(progress) = &search.progress {
+            progress.finish();
+        }
+    };
+
+    let mut stdout = search.writer();
+
+    let deadline = search.max_time.map(|d| std::time::Instant::now() + d);
+    // The error is true if the search timed out, false if it finished
+    // Results that are already waiting are received even after the deadline, so it's checked first
+    let recv = || match deadline {
+        Some(deadline) if std::time::Instant::now() >= deadline => Err(true),
+        Some(deadline) => receiver.recv_deadline(deadline).map_err(|e| e.is_timeout()),
+        None => receiver.recv().map_err(|_| false),
+    };
+
     // -f
     if search.first {
-        let Ok(path) = receiver.recv() else {
-            if search.output == Output::Normal {
-                println!("File not found");
+        let path = recv();
+        finish_progress();
+        let path = match path {
+            Ok(path) => path,
+            Err(timed_out) => {
+                if search.output == Output::Normal {
+                    writeln!(stdout, "File not found").unwrap();
+                }
+                stdout.flush().unwrap();
+                search.print_skipped().unwrap();
+                if timed_out {
+                    search.exit_timed_out()
+                }
+                std::process::exit(search.exit_code(0))
             }
-            std::process::exit(0)
         };
-        println!("{path}");
-        std::process::exit(0)
+        writeln!(stdout, "{}", search.display(path.entry())).unwrap();
+        stdout.flush().unwrap();
+        search.print_skipped().unwrap();
+        std::process::exit(search.exit_code(1))
     }
 
-    let stdout = std::io::stdout();
-    let mut stdout = std::io::BufWriter::new(stdout.lock());
-
     // -ss
-    if search.output == Output::SuperSimple {
-        while let Ok(path) = receiver.recv() {
-            writeln!(stdout, "{path}").unwrap();
-        }
+    if search.streams() {
+        let mut found = 0;
+        let mut counts = [0; 2];
+        let timed_out = loop {
+            match recv() {
+                Ok(path) if search.over_type_limit(&path, &mut counts) => (),
+                Ok(path) => {
+                    writeln!(stdout, "{}", search.display(path.entry())).unwrap();
+                    found += !matches!(path, SearchResult::Sibling(_)) as usize;
+                }
+                Err(timed_out) => break timed_out,
+            }
+        };
+        finish_progress();
         stdout.flush().unwrap();
-        std::process::exit(0)
+        search.print_total_size().unwrap();
+        search.print_skipped().unwrap();
+        search.warn_unreliable_atime();
+        if timed_out {
+            search.exit_timed_out()
+        }
+        search.check_min_results(found);
+        std::process::exit(search.exit_code(found))
     }
 
+    drop(stdout);
     let mut exact = Vec::with_capacity(8);
     let mut contains = Vec::with_capacity(8);
-    while let Ok(path) = receiver.recv() {
-        match path {
+    let mut siblings = Vec::new();
+    let mut memory = 0u64;
+    let mut counts = [0; 2];
+    let timed_out = loop {
+        let result = match recv() {
+            Ok(result) => result,
+            Err(timed_out) => break timed_out,
+        };
+        if search.over_type_limit(&result, &mut counts) {
+            continue;
+        }
+        // Results over the limit are still received, so the search can finish
+        if let Some(limit) = search.limit_memory {
+            let size = result.entry().memory_size() as u64;
+            if memory + size > limit {
+                search.truncated.store(true, Ordering::Relaxed);
+                continue;
+            }
+            memory += size;
+        }
+        match result {
             SearchResult::Contains(path) => contains.push(path),
             SearchResult::Exact(path) => exact.push(path),
+            SearchResult::Sibling(path) => siblings.push(path),
+        }
+    };
+    finish_progress();
+    search.warn_unreliable_atime();
+    // The results are printed here, as the threads still searching (e.g. stuck on a slow mount) would never be joined
+    if timed_out {
+        search.print_results((exact, contains, siblings)).unwrap();
+        search.print_total_size().unwrap();
+        search.exit_timed_out()
+    }
+    (exact, contains, siblings)
+}
+
+impl Search {
+    /// Checks the search root itself against the query if `--match-roots` is enabled.
+    fn match_root(&self, root: &Path, sink: Sink) {
+        // Roots like "." or "/" don't have a name to match
+        if !self.match_roots || root.file_name().is_none() {
+            return;
+        }
+        self.match_path(root, sink)
+    }
+
+    /// If the results must know the search root they were found in, for `--relative-roots` and `--du`.
+    fn needs_root(&self) -> bool {
+        self.relative_roots || self.disk_usage
+    }
+
+    /// Returns true if `result` is over the `--max-files` or `--max-dirs` cap, counting it in `counts` otherwise.
+    ///
+    /// `counts` holds the files and directories kept so far, siblings are never counted.
+    fn over_type_limit(&self, result: &SearchResult, counts: &mut [usize; 2]) -> bool {
+        if matches!(result, SearchResult::Sibling(_)) {
+            return false;
+        }
+        let is_dir = result.entry().is_dir;
+        let max = [self.max_files, self.max_dirs][is_dir as usize];
+        let count = &mut counts[is_dir as usize];
+        if max.is_some_and(|max| *count >= max) {
+            return true;
+        }
+        *count += 1;
+        false
+    }
+
+    /// Checks `path` against the query without traversing it.
+    fn match_path(&self, path: &Path, sink: Sink) {
+        let _permit = self.open_permit(false);
+        let Ok(candidate) = PathCandidate::new(path.to_path_buf()) else {
+            if self.verbose {
+                eprintln!("Could not read {:?}", path);
+            }
+            return;
+        };
+        if let Some((Some(result), _)) = is_result(candidate, self) {
+            if let Some(progress) = &self.progress {
+                progress.found();
+            }
+            sink(result);
+        }
+    }
+
+    /// Returns `path` relative to the `--relative-to` directory, or `None` if the results are printed as found.
+    fn relative_path(&self, path: &Path) -> Option<std::path::PathBuf> {
+        let (base, current_dir) = self.relative_to.as_ref()?;
+        let path = crate::print::normalize(&current_dir.join(path));
+        Some(crate::print::make_relative(base, &path).unwrap_or(path))
+    }
+
+    /// Returns true if `path` will be changed by the `--quote-style` when printed.
+    fn needs_quoting(&self, path: &str) -> bool {
+        let quoted = || crate::print::quote(self.quote_style, path);
+        self.quote_style != QuoteStyle::Literal
+            && !self.show_offsets
+            && !self.summary_json
+            && matches!(quoted(), std::borrow::Cow::Owned(_))
+    }
+
+    /// Returns true if the directory at `path` must not be traversed: a `.git` directory with `--skip-git`, a directory in the default ignore list, or one excluded with `--exclude-dir`.
+    fn skips(&self, path: &Path) -> bool {
+        let Some(name) = file_name(path).and_then(|name| name.to_str()) else {
+            return false;
+        };
+        let reason = if self.skip_git && name == ".git" {
+            Skip::Git
+        } else if self.hardcoded_ignore.contains(name) {
+            Skip::DefaultIgnore
+        } else if self.exclude_dirs.iter().any(|dir| dir == name) {
+            Skip::ExcludeDir
+        } else {
+            return false;
+        };
+        self.record_skip(path, reason);
+        true
+    }
+
+    /// Waits for the `--max-open-files` permits needed to check a path, or to read a directory and check its entries.
+    ///
+    /// A permit is reserved for the file read by `--skip-binary` and `--only-binary`, so no more are taken while holding these.
+    fn open_permit(&self, dir: bool) -> Option<crate::semaphore::Permit<'_>> {
+        let n = dir as usize + self.binary.is_some() as usize;
+        let open_files = self.open_files.as_ref().filter(|_| n > 0);
+        open_files.map(|s| s.acquire(n))
+    }
+
+    /// Adds the entry to the ones reported with `--report-skipped`.
+    fn record_skip(&self, path: &Path, reason: Skip) {
+        if let Some(skipped) = &self.skipped {
+            skipped.record(path, reason);
+        }
+    }
+
+    /// Depth limit of the directory at `path`, which is `depth` levels below its search root.
+    ///
+    /// A followed symlink can only be traversed `--follow-max-depth` levels, or less if the limit of its parent is stricter.
+    fn follow_limit(&self, path: &Path, depth: usize, depth_limit: usize) -> usize {
+        match self.follow_max_depth {
+            Some(max) if path.is_symlink() => depth_limit.min(depth.saturating_add(max)),
+            _ => depth_limit,
+        }
+    }
+
+    /// Returns true if the symlink at `path` points to a directory that must be searched with `--follow-under`.
+    ///
+    /// Each target is followed only once, and never if it contains the link, so links can't make the search loop.
+    fn follows(&self, path: &Path) -> bool {
+        let Ok(target) = path.canonicalize() else {
+            return false;
+        };
+        if !target.is_dir() || !self.follow_under.iter().any(|dir| target.starts_with(dir)) {
+            return false;
+        }
+        let parent = path.parent().and_then(|p| p.canonicalize().ok());
+        if parent.is_some_and(|p| p.starts_with(&target)) {
+            if self.verbose {
+                eprintln!("Not following {:?}, it points to one of its parents", path);
+            }
+            return false;
+        }
+        self.followed.lock().unwrap().insert(target)
+    }
+
+    /// If the results are printed as soon as they are found (`-ss`), instead of after the search.
+    fn streams(&self) -> bool {
+        self.output == Output::SuperSimple
+            && self.benchmark.is_none()
+            && !self.deterministic
+            && !self.interactive
+    }
+
+    /// Exits with an error after `--max-time`, once the partial results have been printed.
+    fn exit_timed_out(&self) -> ! {
+        eprintln!("Search timed out, results may be incomplete");
+        std::process::exit(2)
+    }
+
+    /// Exit code once the search is finished: 2 if it was incomplete, 1 if nothing was found and 0 otherwise.
+    pub fn exit_code(&self, found: usize) -> i32 {
+        if self.incomplete.load(Ordering::Relaxed) || self.truncated.load(Ordering::Relaxed) {
+            2
+        } else if found == 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Exits with an error if less than `--min-results` matches were found.
+    pub fn check_min_results(&self, found: usize) {
+        if found < self.min_results {
+            eprintln!(
+                "Error: Found {found} results, but at least {} were expected",
+                self.min_results
+            );
+            std::process::exit(1)
+        }
+    }
+
+    /// Returns the first filter that needs the metadata which leaves the entry out, if any.
+    ///
+    /// Only called for entries that already match the query, as it requires an extra stat.
+    #[profi::profile]
+    fn metadata_skip(&self, entry: &impl Candidate) -> Option<Skip> {
+        if self.accessed_after.is_none()
+            && self.accessed_before.is_none()
+            && self.mtime_matches.is_none()
+            && self.modified_after.is_none()
+            && !self.executable
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.binary.is_none()
+        {
+            return None;
+        }
+        if self.executable && !is_executable(&entry.path()) {
+            return Some(Skip::Executable);
+        }
+        let Ok(metadata) = entry.metadata() else {
+            return Some(Skip::Metadata);
+        };
+        if !self.matches_size(&metadata) {
+            Some(Skip::Size)
+        } else if !self.matches_atime(entry, &metadata) || !self.matches_mtime(entry, &metadata) {
+            Some(Skip::Time)
+        } else if !self.matches_binary(entry, &metadata) {
+            Some(Skip::Binary)
+        } else {
+            None
+        }
+    }
+
+    /// Checks the contents of files against `--skip-binary` and `--only-binary`, directories never match them.
+    ///
+    /// It's the last check, as it's the only one that reads the files.
+    fn matches_binary(&self, entry: &impl Candidate, metadata: &std::fs::Metadata) -> bool {
+        let Some(binary) = self.binary else {
+            return true;
+        };
+        if metadata.is_dir() {
+            return false;
+        }
+        match is_binary(&entry.path()) {
+            Ok(is_binary) => is_binary == binary,
+            Err(e) => {
+                if self.verbose {
+                    eprintln!("Could not read {:?}: {e}", entry.path());
+                }
+                false
+            }
+        }
+    }
+
+    /// Returns false if another name of the same file was already found, with `--unique`.
+    ///
+    /// Entries that can't be identified are always kept.
+    fn is_first_link(&self, entry: &impl Candidate) -> bool {
+        let Some(unique) = &self.unique else {
+            return true;
+        };
+        match entry.file_id() {
+            Some(id) => unique.lock().unwrap().insert(id),
+            None => true,
+        }
+    }
+
+    /// Reads the time the results are sorted by with `--sort accessed` or `--sort created`.
+    ///
+    /// Where creation times are not available, the modification time is used instead, which is reported once with `--verbose`.
+    fn sort_time(&self, entry: &impl Candidate) -> Option<std::time::SystemTime> {
+        if !matches!(self.sort, Sort::Accessed | Sort::Created) {
+            return None;
+        }
+        let metadata = entry.metadata().ok()?;
+        if self.sort == Sort::Accessed {
+            return metadata.accessed().ok();
+        }
+        metadata.created().ok().or_else(|| {
+            let warned = self.created_unavailable.swap(true, Ordering::Relaxed);
+            if self.verbose && !warned {
+                eprintln!("Warning: Creation times are not available for some files, like {:?}, their modification time is used instead", entry.path());
+            }
+            metadata.modified().ok()
+        })
+    }
+
+    /// Checks the size of files against `--min-size` and `--max-size`, directories never match them.
+    fn matches_size(&self, metadata: &std::fs::Metadata) -> bool {
+        if self.min_size.is_none() && self.max_size.is_none() {
+            return true;
+        }
+        !metadata.is_dir()
+            && self.min_size.map_or(true, |min| metadata.len() >= min)
+            && self.max_size.map_or(true, |max| metadata.len() <= max)
+    }
+
+    fn matches_atime(&self, entry: &impl Candidate, metadata: &std::fs::Metadata) -> bool {
+        if self.accessed_after.is_none() && self.accessed_before.is_none() {
+            return true;
+        }
+        let Ok(accessed) = metadata.accessed() else {
+            if self.verbose {
+                eprintln!("Could not read access time of {:?}", entry.path());
+            }
+            return false;
+        };
+        self.atime_checked.fetch_add(1, Ordering::Relaxed);
+        if metadata.modified().is_ok_and(|m| m == accessed) {
+            self.atime_equals_mtime.fetch_add(1, Ordering::Relaxed);
+        }
+        self.accessed_after.map_or(true, |t| accessed >= t)
+            && self.accessed_before.map_or(true, |t| accessed < t)
+    }
+
+    /// Checks the modification time against `--since-file`, and formatted with `--time-format` against `--mtime-matches`.
+    fn matches_mtime(&self, entry: &impl Candidate, metadata: &std::fs::Metadata) -> bool {
+        if self.mtime_matches.is_none() && self.modified_after.is_none() {
+            return true;
+        }
+        let Ok(modified) = metadata.modified() else {
+            if self.verbose {
+                eprintln!("Could not read modification time of {:?}", entry.path());
+            }
+            return false;
+        };
+        if self.modified_after.is_some_and(|t| modified <= t) {
+            return false;
+        }
+        let Some(pattern) = &self.mtime_matches else {
+            return true;
+        };
+        self.format_time(modified).contains(pattern.as_str())
+    }
+
+    /// Formats `time` with the `--time-format`, in UTC with `--time-utc` or in local time otherwise.
+    fn format_time(&self, time: std::time::SystemTime) -> String {
+        if self.time_utc {
+            let time = chrono::DateTime::<chrono::Utc>::from(time);
+            time.format(&self.time_format).to_string()
+        } else {
+            let time = chrono::DateTime::<chrono::Local>::from(time);
+            time.format(&self.time_format).to_string()
+        }
+    }
+
+    /// Warns (under `--verbose`) if most of the checked access times are equal to the modification times.
+    ///
+    /// That usually means the filesystem is mounted with `noatime`/`relatime`, so access times can't be trusted.
+    fn warn_unreliable_atime(&self) {
+        let checked = self.atime_checked.load(Ordering::Relaxed);
+        let equal = self.atime_equals_mtime.load(Ordering::Relaxed);
+        if self.verbose && checked >= 16 && equal * 10 >= checked * 9 {
+            eprintln!("Warning: {equal} of {checked} files have the same access and modification time, access times may be unreliable on this filesystem (noatime/relatime)");
         }
     }
-    (exact, contains)
 }
 
 /// from https://github.com/BurntSushi/ripgrep/blob/master/crates/ignore/src/pathutil.rs
@@ -276,7 +990,7 @@ pub(crate) fn is_hidden(path: &Path) -> bool {
 /// * The file attributes have the `HIDDEN` property set.
 #[cfg(windows)]
 #[inline(always)]
-pub(crate) fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+pub(crate) fn is_hidden(entry: &impl Candidate) -> bool {
     use std::os::windows::fs::MetadataExt;
     use winapi_util::file;
 
@@ -323,3 +1037,553 @@ pub(crate) fn file_name(path: &Path) -> Option<&std::ffi::OsStr> {
 pub(crate) fn file_name<'a, P: AsRef<Path> + ?Sized>(path: &'a P) -> Option<&'a std::ffi::OsStr> {
     path.as_ref().file_name()
 }
+
+/// Returns the type code of an entry printed with `--show-type`, '?' if its type couldn't be read.
+fn type_tag(ftype: Option<std::io::Result<std::fs::FileType>>) -> char {
+    let Some(Ok(ftype)) = ftype else {
+        return '?';
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if ftype.is_fifo() {
+            return 'p';
+        } else if ftype.is_socket() {
+            return 's';
+        } else if ftype.is_block_device() {
+            return 'b';
+        } else if ftype.is_char_device() {
+            return 'c';
+        }
+    }
+    if ftype.is_symlink() {
+        'l'
+    } else if ftype.is_dir() {
+        'd'
+    } else {
+        'f'
+    }
+}
+
+/// Returns true if the first 8 KiB of the file at `path` contain a NUL byte, following symlinks.
+fn is_binary(path: &Path) -> std::io::Result<bool> {
+    use std::io::Read;
+    let mut head = Vec::with_capacity(8192);
+    let file = std::fs::File::open(path)?;
+    file.take(8192).read_to_end(&mut head)?;
+    Ok(memchr::memchr(0, &head).is_some())
+}
+
+/// Returns true if `path` is an executable file, following symlinks.
+///
+/// On Windows, files are executable if their extension is in `PATHEXT`.
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(windows)]
+    {
+        let path_ext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return false;
+        };
+        path.is_file()
+            && path_ext
+                .split(';')
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        path.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    /// Runs `search` and returns the paths found with their depths, sorted.
+    fn found_depths(search: &Search) -> Vec<(String, usize)> {
+        let found = std::sync::Mutex::new(Vec::new());
+        search.search_with_sink(&|result| {
+            let entry = result.entry();
+            let path = entry.path.to_string();
+            found.lock().unwrap().push((path, entry.depth));
+        });
+        let mut found = found.into_inner().unwrap();
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn nested_roots_measure_depth_from_their_root() {
+        let tmp = std::env::temp_dir().join(format!("hunt-nested-roots-{}", std::process::id()));
+        let (a, b) = (tmp.join("a"), tmp.join("a").join("b"));
+        std::fs::create_dir_all(b.join("c")).unwrap();
+        std::fs::write(b.join("c").join("needle"), "").unwrap();
+        let needle = b.join("c").join("needle").to_string_lossy().into_owned();
+        let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+        // The file is found once from each root, 3 levels below '/a' and 2 below '/a/b'
+        let search = Search::from_args(&["needle", a, b]);
+        let depths = found_depths(&search);
+
+        // With a limit of 2 levels, only the search from '/a/b' reaches it
+        let (a_limited, b_limited) = (format!("{a}:2"), format!("{b}:2"));
+        let search = Search::from_args(&["needle", &a_limited, &b_limited]);
+        let limited = found_depths(&search);
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+        assert_eq!(depths, [(needle.clone(), 2), (needle.clone(), 3)]);
+        assert_eq!(limited, [(needle, 2)]);
+    }
+
+    #[test]
+    fn limited_and_unlimited_roots() {
+        let entries = ["a/needle", "a/x/y/needle", "b/needle", "b/x/y/needle"];
+        let dir = TestDir::new("mixed-depth-limits", &entries);
+        let (a, b) = (format!("{}/a:1", dir.arg()), format!("{}/b", dir.arg()));
+        let search = Search::from_args(&["needle", &a, &b]);
+        let found = dir.found_paths(&search);
+        assert_eq!(found, ["a/needle", "b/needle", "b/x/y/needle"]);
+
+        // A limit reaching the deepest entries finds them all
+        let (a, b) = (format!("{}/a:3", dir.arg()), format!("{}/b:1", dir.arg()));
+        let search = Search::from_args(&["needle", &a, &b]);
+        let found = dir.found_paths(&search);
+        assert_eq!(found, ["a/needle", "a/x/y/needle", "b/needle"]);
+    }
+
+    #[test]
+    fn absolute_glob_ignores_the_caches_of_every_user() {
+        let entries = [
+            "home/alice/.cache/needle",
+            "home/bob/.cache/needle",
+            "home/bob/.cache/deep/needle",
+            "home/bob/docs/needle",
+            "home/bob/docs/.cache/needle",
+        ];
+        let dir = TestDir::new("absolute-ignore-glob", &entries);
+        let glob = format!("{}/home/*/.cache", dir.arg());
+        let search = Search::from_args(&["--hidden", "-i", &glob, "needle", dir.arg()]);
+        let found = dir.found_paths(&search);
+        assert_eq!(
+            found,
+            ["home/bob/docs/.cache/needle", "home/bob/docs/needle"]
+        );
+
+        // Plain absolute paths are still only matched exactly
+        let plain = format!("{}/home/bob/docs", dir.arg());
+        let search = Search::from_args(&["--hidden", "-i", &plain, "needle", dir.arg()]);
+        let found = dir.found_paths(&search);
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|path| !path.starts_with("home/bob/docs")));
+    }
+
+    /// Paths of the exact matches and of the contains matches found by `search` in `dir`.
+    fn exact_and_contains(dir: &TestDir, search: &Search) -> (Vec<String>, Vec<String>) {
+        let (exact, contains): (Vec<_>, Vec<_>) = dir
+            .found(search)
+            .into_iter()
+            .partition(|(_, result)| matches!(result, SearchResult::Exact(_)));
+        let paths =
+            |found: Vec<(String, SearchResult)>| found.into_iter().map(|(p, _)| p).collect();
+        (paths(exact), paths(contains))
+    }
+
+    #[test]
+    fn exact_exact_stem_and_contains() {
+        let entries = ["foo", "foo.rs", "foo.tar.gz", "foobar.rs", "a-foo.rs"];
+        let dir = TestDir::new("exact-stem", &entries);
+
+        let search = Search::from_args(&["foo", dir.arg()]);
+        let (exact, contains) = exact_and_contains(&dir, &search);
+        assert_eq!(exact, ["foo"]);
+        assert_eq!(contains, ["a-foo.rs", "foo.rs", "foo.tar.gz", "foobar.rs"]);
+
+        let search = Search::from_args(&["--exact", "foo", dir.arg()]);
+        let (exact, contains) = exact_and_contains(&dir, &search);
+        assert_eq!(exact, ["foo"]);
+        assert!(contains.is_empty());
+
+        // Only the last extension is removed
+        let search = Search::from_args(&["--exact-stem", "foo", dir.arg()]);
+        let (exact, contains) = exact_and_contains(&dir, &search);
+        assert_eq!(exact, ["foo", "foo.rs"]);
+        assert!(contains.is_empty());
+
+        let search = Search::from_args(&["--exact-stem", "--ends", ".rs", "foo", dir.arg()]);
+        let (exact, contains) = exact_and_contains(&dir, &search);
+        assert_eq!(exact, ["foo.rs"]);
+        assert!(contains.is_empty());
+    }
+
+    #[test]
+    fn require_all_rejects_partial_matches() {
+        let entries = [
+            "integration_test.rs",
+            "test_integration.py",
+            "test_unit.rs",
+            "integration.rs",
+            "Integration-Test.md",
+            "test",
+        ];
+        let dir = TestDir::new("require-all", &entries);
+        let search = Search::from_args(&["--require-all", "test,integration", dir.arg()]);
+        let (exact, contains) = exact_and_contains(&dir, &search);
+        assert!(exact.is_empty());
+        let all = [
+            "Integration-Test.md",
+            "integration_test.rs",
+            "test_integration.py",
+        ];
+        assert_eq!(contains, all);
+
+        // An uppercase substring makes it case sensitive, like the query
+        let args = [
+            "--smart-case",
+            "--require-all",
+            "Test,integration",
+            dir.arg(),
+        ];
+        assert!(Search::from_args(&args).case_sensitive);
+        let args = [
+            "--smart-case",
+            "--require-all",
+            "Test,Integration",
+            dir.arg(),
+        ];
+        let search = Search::from_args(&args);
+        assert_eq!(dir.found_paths(&search), ["Integration-Test.md"]);
+        // The query must still be in the name
+        let args = ["--require-all", "test,integration", "rs", dir.arg()];
+        let search = Search::from_args(&args);
+        assert_eq!(dir.found_paths(&search), ["integration_test.rs"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directories_are_not_traversed() {
+        let dir = TestDir::new("symlinked-dir", &["real/needle", "real/sub/needle"]);
+        std::os::unix::fs::symlink(dir.join("real"), dir.join("link")).unwrap();
+        let search = Search::from_args(&["needle", dir.arg()]);
+        assert_eq!(dir.found_paths(&search), ["real/needle", "real/sub/needle"]);
+
+        let search = Search::from_args(&["-t", "l", "", dir.arg()]);
+        assert_eq!(dir.found_paths(&search), ["link"]);
+        let search = Search::from_args(&["-t", "d", "", dir.arg()]);
+        assert_eq!(dir.found_paths(&search), ["real", "real/sub"]);
+        let search = Search::from_args(&["--show-type", "link", dir.arg()]);
+        let found = dir.found(&search);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.entry().extra().type_tag, Some('l'));
+    }
+
+    #[test]
+    fn traversing_and_matching_hidden_entries() {
+        let entries = [
+            ".hidden/needle",
+            ".hidden/.needle",
+            "visible/.needle",
+            "visible/needle",
+        ];
+        let dir = TestDir::new("hidden", &entries);
+        let found = |flags: &[&str]| {
+            let args = [flags, &["needle", dir.arg()]].concat();
+            dir.found_paths(&Search::from_args(&args))
+        };
+        assert_eq!(found(&[]), ["visible/needle"]);
+        assert_eq!(
+            found(&["--traverse-hidden"]),
+            [".hidden/needle", "visible/needle"]
+        );
+        assert_eq!(
+            found(&["--match-hidden"]),
+            ["visible/.needle", "visible/needle"]
+        );
+        let all = [
+            ".hidden/.needle",
+            ".hidden/needle",
+            "visible/.needle",
+            "visible/needle",
+        ];
+        assert_eq!(found(&["--traverse-hidden", "--match-hidden"]), all);
+        assert_eq!(found(&["--hidden"]), all);
+    }
+
+    #[test]
+    fn size_range() {
+        let dir = TestDir::new("size-range", &[]);
+        for (name, len) in [("small", 512), ("medium", 500 << 10), ("big", 2 << 20)] {
+            let file = std::fs::File::create(dir.join(name)).unwrap();
+            file.set_len(len).unwrap();
+        }
+        let found = |flags: &[&str]| {
+            let args = [flags, &["", dir.arg()]].concat();
+            dir.found_paths(&Search::from_args(&args))
+        };
+        assert_eq!(found(&["--min-size", "1k", "--max-size", "1M"]), ["medium"]);
+        assert_eq!(found(&["--min-size", "1k"]), ["big", "medium"]);
+        assert_eq!(found(&["--max-size", "512"]), ["small"]);
+    }
+
+    #[test]
+    fn files_modified_after_the_reference() {
+        let dir = TestDir::new("since-file", &["before"]);
+        let modified = |name| {
+            std::fs::metadata(dir.join(name))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        // Each file is written again until its mtime is after the previous one, whatever the precision of the filesystem
+        for (name, previous) in [("reference", "before"), ("after", "reference")] {
+            dir.create(name);
+            while modified(name) <= modified(previous) {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                dir.create(name);
+            }
+        }
+        let reference = dir.join("reference");
+        let args = ["--since-file", reference.to_str().unwrap(), "", dir.arg()];
+        assert_eq!(dir.found_paths(&Search::from_args(&args)), ["after"]);
+    }
+
+    #[test]
+    fn exclude_dir_skips_only_directories() {
+        let entries = [
+            "build/",
+            "build/a.txt",
+            "src/",
+            "src/build",
+            "src/lib/",
+            "src/lib/build/",
+            "src/lib/build/b.txt",
+        ];
+        let dir = TestDir::new("exclude-dir", &entries);
+        let search = Search::from_args(&["--exclude-dir", "build", "", dir.arg()]);
+        // The excluded directories are still results, but their contents are not searched
+        let found = ["build", "src", "src/build", "src/lib", "src/lib/build"];
+        assert_eq!(dir.found_paths(&search), found);
+    }
+
+    #[test]
+    fn parent_directory_name() {
+        let entries = [
+            "migrations/up.sql",
+            "migrations/nested/up.sql",
+            "db/Migrations/up.sql",
+            "other/up.sql",
+        ];
+        let dir = TestDir::new("parent", &entries);
+        let found = |args: &[&str]| {
+            let args = [args, &["up.sql", dir.arg()]].concat();
+            dir.found_paths(&Search::from_args(&args))
+        };
+        let ignoring_case = ["db/Migrations/up.sql", "migrations/up.sql"];
+        assert_eq!(found(&["-I", "--parent", "migrations"]), ignoring_case);
+        assert_eq!(
+            found(&["-C", "--parent", "migrations"]),
+            ["migrations/up.sql"]
+        );
+        // An uppercase parent makes the search case sensitive, like an uppercase query
+        assert_eq!(found(&["--parent", "Migrations"]), ["db/Migrations/up.sql"]);
+    }
+
+    #[test]
+    fn sort_time_is_read_for_the_time_keys() {
+        let dir = TestDir::new("sort-time", &["a.txt", "b/"]);
+        // Without creation times the modification time is used, so it's always read
+        for (key, read) in [("accessed", true), ("created", true), ("name", false)] {
+            let search = Search::from_args(&["--sort", key, "", dir.arg()]);
+            for (path, result) in dir.found(&search) {
+                let sort_time = result.entry().extra().sort_time;
+                assert_eq!(sort_time.is_some(), read, "{key} {path}");
+            }
+        }
+    }
+
+    #[test]
+    fn time_format_in_utc() {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let args = [
+            "--mtime-matches",
+            "2023",
+            "--time-utc",
+            "--time-format",
+            "%a %d/%m/%Y %H:%M:%S",
+            "",
+        ];
+        let search = Search::from_args(&args);
+        assert_eq!(search.format_time(time), "Tue 14/11/2023 22:13:20");
+        let search = Search::from_args(&["--mtime-matches", "2023", "--time-utc", ""]);
+        assert_eq!(search.format_time(time), "2023-11-14 22:13");
+    }
+
+    #[test]
+    fn binary_files_have_a_nul_in_their_first_8_kib() {
+        let dir = TestDir::new("binary", &["empty", "sub/"]);
+        std::fs::write(dir.join("text.txt"), "hello\n").unwrap();
+        std::fs::write(dir.join("nul.bin"), b"ab\0cd").unwrap();
+        let late_nul = [&[b'a'; 8192][..], b"\0"].concat();
+        std::fs::write(dir.join("late-nul"), late_nul).unwrap();
+        // Directories are neither text nor binary files
+        let skip = Search::from_args(&["--skip-binary", "", dir.arg()]);
+        assert_eq!(dir.found_paths(&skip), ["empty", "late-nul", "text.txt"]);
+        let only = Search::from_args(&["--only-binary", "", dir.arg()]);
+        assert_eq!(dir.found_paths(&only), ["nul.bin"]);
+    }
+
+    #[test]
+    fn no_literal_separator_lets_stars_match_nested_paths() {
+        let dir = TestDir::new(
+            "literal-separator",
+            &["src/a.rs", "src/nested/b.rs", "src/nested/c.txt"],
+        );
+        let glob = format!("{}/src/*.rs", dir.arg());
+        let rules = dir.join("rules");
+        std::fs::write(&rules, "src/*.rs\n").unwrap();
+        let rules = rules.to_str().unwrap();
+        let strict = [
+            "rules",
+            "src",
+            "src/nested",
+            "src/nested/b.rs",
+            "src/nested/c.txt",
+        ];
+        let loose = ["rules", "src", "src/nested", "src/nested/c.txt"];
+        for ignore in [["-i", &glob], ["--ignore-file", rules]] {
+            let search = Search::from_args(&[&ignore[..], &["", dir.arg()]].concat());
+            assert_eq!(dir.found_paths(&search), strict, "{ignore:?}");
+            let args = [&ignore[..], &["--no-literal-separator", "", dir.arg()]].concat();
+            assert_eq!(
+                dir.found_paths(&Search::from_args(&args)),
+                loose,
+                "{ignore:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn glob_from_matches_any_glob_except_the_negated() {
+        let dir = TestDir::new(
+            "glob-from",
+            &["a.tmp", "b.bak", "keep.tmp", "c.txt", "sub/d.tmp"],
+        );
+        let globs = dir.join("globs");
+        std::fs::write(&globs, "# Cleanup\n*.tmp\n\n*.bak\n!keep.tmp\n").unwrap();
+        let globs = globs.to_str().unwrap();
+        let search = Search::from_args(&["--glob-from", globs, "", dir.arg()]);
+        assert_eq!(dir.found_paths(&search), ["a.tmp", "b.bak", "sub/d.tmp"]);
+        // The globs are checked in addition to the query
+        let search = Search::from_args(&["--glob-from", globs, "d", dir.arg()]);
+        assert_eq!(dir.found_paths(&search), ["sub/d.tmp"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_max_depth_limits_the_followed_links() {
+        let entries = [
+            "search/",
+            "deep/needle-1",
+            "deep/a/needle-2",
+            "deep/a/b/needle-3",
+            "deep/a/b/c/needle-4",
+        ];
+        let dir = TestDir::new("follow-max-depth", &entries);
+        std::os::unix::fs::symlink(dir.join("deep"), dir.join("search/link")).unwrap();
+        let deep = dir.join("deep");
+        // A link to one of its parents is never followed, so it can't make the search loop
+        std::os::unix::fs::symlink(&deep, dir.join("deep/a/loop")).unwrap();
+        let found = |max_depth: &str, root: &str| {
+            let follow = ["--follow-under", deep.to_str().unwrap()];
+            let root = format!("{}/{root}", dir.arg());
+            let args = [
+                &follow[..],
+                &["--follow-max-depth", max_depth, "needle", &root],
+            ]
+            .concat();
+            dir.found_paths(&Search::from_args(&args))
+        };
+        let all = [
+            "search/link/a/b/c/needle-4",
+            "search/link/a/b/needle-3",
+            "search/link/a/needle-2",
+            "search/link/needle-1",
+        ];
+        assert_eq!(found("10", "search"), all);
+        assert_eq!(
+            found("2", "search"),
+            ["search/link/a/needle-2", "search/link/needle-1"]
+        );
+        // The depth limit of the search directory applies too, the stricter of both is used
+        assert_eq!(found("10", "search:2"), ["search/link/needle-1"]);
+        assert_eq!(found("1", "search:3"), ["search/link/needle-1"]);
+    }
+
+    #[test]
+    fn git_status_of_the_results() {
+        let dir = TestDir::new(
+            "git-status",
+            &["outside.txt", "repo/committed.txt", "repo/modified.txt"],
+        );
+        let repo = dir.join("repo");
+        let git = |args: &[&str]| {
+            let config = [
+                "-c",
+                "user.name=hunt",
+                "-c",
+                "user.email=hunt@example.com",
+                "-c",
+                "commit.gpgsign=false",
+            ];
+            let mut git = std::process::Command::new("git");
+            git.arg("-C").arg(&repo).args(config).args(args);
+            git.output().is_ok_and(|output| output.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            eprintln!("Skipping the test, git is not available");
+            return;
+        }
+        assert!(git(&["add", "."]) && git(&["commit", "-q", "-m", "Initial commit"]));
+        std::fs::write(dir.join("repo/modified.txt"), "changed").unwrap();
+        dir.create("repo/staged.txt");
+        assert!(git(&["add", "staged.txt"]));
+        dir.create("repo/untracked.txt");
+
+        let search = Search::from_args(&["--git-status", "txt", dir.arg()]);
+        let found = dir.found(&search).into_iter().map(|(path, result)| {
+            let status = result.entry().extra().git_status.unwrap();
+            (path, String::from_utf8(status.to_vec()).unwrap())
+        });
+        let expected = [
+            ("outside.txt", "  "),
+            ("repo/committed.txt", "  "),
+            ("repo/modified.txt", " M"),
+            ("repo/staged.txt", "A "),
+            ("repo/untracked.txt", "??"),
+        ];
+        let expected = expected.map(|(path, status)| (path.to_owned(), status.to_owned()));
+        assert_eq!(found.collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn max_open_files_still_finds_every_file() {
+        let entries =
+            (0..20).flat_map(|i| [format!("{i}/"), format!("{i}/a.txt"), format!("{i}/b.txt")]);
+        let entries = entries.collect::<Vec<_>>();
+        let dir = TestDir::new(
+            "max-open-files",
+            &entries.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+        // With '--skip-binary' each directory needs the minimum 2 permits, one for itself and one for the file read
+        for extra in [&[][..], &["--skip-binary"]] {
+            let args = [extra, &["--max-open-files", "2", "-t", "f", "", dir.arg()]].concat();
+            let search = Search::from_args(&args);
+            assert_eq!(dir.found_paths(&search).len(), 40, "{args:?}");
+        }
+    }
+}
@@ -1,24 +1,213 @@
 pub type Path = thin_str::ThinStr;
 
 pub enum SearchResult {
-    Contains(Path),
-    Exact(Path),
+    Contains(Entry),
+    Exact(Entry),
+    /// Entry in the same directory as a match.
+    Sibling(Entry),
 }
 
+/// A single match, as stored in the result buffers.
+pub struct Entry {
+    pub path: Path,
+    pub is_dir: bool,
+    /// If the path must be quoted when printed, in which case it's not highlighted.
+    pub quoted: bool,
+    /// Levels below the search root, its direct entries are at depth 1.
+    pub depth: usize,
+    /// Bytes at the start of the path taken by its search root, skipped with `--relative-roots`.
+    pub root_len: usize,
+    /// Data only read with some flags, allocated when one of them is set so the buffers stay small.
+    extra: Option<Box<Extra>>,
+}
+
+/// Data of an [`Entry`] that most searches don't need.
+#[derive(Default)]
+pub struct Extra {
+    /// Inode number printed with `--inode`.
+    pub inode: Option<u64>,
+    /// Type code printed with `--show-type`, like the first column of `ls -l`.
+    pub type_tag: Option<char>,
+    /// Status letters printed with `--git-status`.
+    pub git_status: Option<[u8; 2]>,
+    /// Modification time, only read with `--newest` or `--oldest`.
+    pub modified: Option<std::time::SystemTime>,
+    /// Access or creation time, only read with `--sort accessed` or `--sort created`.
+    pub sort_time: Option<std::time::SystemTime>,
+    /// Size of the file, only read with `--du`.
+    pub size: Option<u64>,
+    /// Byte range of the query in the file name, printed with `--show-offsets`.
+    pub match_span: Option<(usize, usize)>,
+}
+
+const NO_EXTRA: Extra = Extra {
+    inode: None,
+    type_tag: None,
+    git_status: None,
+    modified: None,
+    sort_time: None,
+    size: None,
+    match_span: None,
+};
+
 impl SearchResult {
-    pub fn contains(path: String) -> Self {
-        Self::Contains(path.into())
+    pub fn contains(path: String, is_dir: bool) -> Self {
+        Self::Contains(Entry::new(path, is_dir))
+    }
+    pub fn exact(path: String, is_dir: bool) -> Self {
+        Self::Exact(Entry::new(path, is_dir))
+    }
+    pub fn sibling(path: String, is_dir: bool) -> Self {
+        Self::Sibling(Entry::new(path, is_dir))
+    }
+    /// Sets the inode printed with `--inode`.
+    pub fn with_inode(self, inode: Option<u64>) -> Self {
+        self.set_extra(inode, |extra| &mut extra.inode)
+    }
+    /// Sets the type code printed with `--show-type`.
+    pub fn with_type_tag(self, type_tag: Option<char>) -> Self {
+        self.set_extra(type_tag, |extra| &mut extra.type_tag)
+    }
+    /// Sets the status letters printed with `--git-status`.
+    pub fn with_git_status(self, git_status: Option<[u8; 2]>) -> Self {
+        self.set_extra(git_status, |extra| &mut extra.git_status)
+    }
+    /// Sets the modification time used by `--newest` and `--oldest`.
+    pub fn with_modified(self, modified: Option<std::time::SystemTime>) -> Self {
+        self.set_extra(modified, |extra| &mut extra.modified)
+    }
+    /// Sets the time used by `--sort accessed` and `--sort created`.
+    pub fn with_sort_time(self, sort_time: Option<std::time::SystemTime>) -> Self {
+        self.set_extra(sort_time, |extra| &mut extra.sort_time)
+    }
+    /// Sets the size added up by `--du`.
+    pub fn with_size(self, size: Option<u64>) -> Self {
+        self.set_extra(size, |extra| &mut extra.size)
+    }
+    /// Marks the path to be quoted with the `--quote-style`.
+    pub fn with_quoting(mut self, quoted: bool) -> Self {
+        self.entry_mut().quoted = quoted;
+        self
+    }
+    /// Sets the depth below the search root.
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.entry_mut().depth = depth;
+        self
+    }
+    /// Sets the search root the result was found in, if the path starts with it.
+    pub fn with_root(mut self, root: &std::path::Path) -> Self {
+        let root = root.to_string_lossy();
+        let entry = self.entry_mut();
+        if let Some(rest) = entry.path.strip_prefix(root.as_ref()) {
+            let rest = rest.trim_start_matches(std::path::is_separator);
+            entry.root_len = entry.path.len() - rest.len();
+        }
+        self
+    }
+    /// Sets the range of the query in the file name printed with `--show-offsets`.
+    pub fn with_match_span(self, span: Option<(usize, usize)>) -> Self {
+        self.set_extra(span, |extra| &mut extra.match_span)
+    }
+    pub fn entry(&self) -> &Entry {
+        match self {
+            Self::Contains(entry) => entry,
+            Self::Exact(entry) => entry,
+            Self::Sibling(entry) => entry,
+        }
+    }
+    /// Stores `value` in the `field` of the extra data, which is only allocated if it's `Some`.
+    fn set_extra<T>(mut self, value: Option<T>, field: fn(&mut Extra) -> &mut Option<T>) -> Self {
+        if value.is_some() {
+            let extra = self.entry_mut().extra.get_or_insert_with(Box::default);
+            *field(extra) = value;
+        }
+        self
+    }
+    fn entry_mut(&mut self) -> &mut Entry {
+        match self {
+            Self::Contains(entry) => entry,
+            Self::Exact(entry) => entry,
+            Self::Sibling(entry) => entry,
+        }
+    }
+}
+
+impl Entry {
+    pub fn new(path: String, is_dir: bool) -> Self {
+        Self {
+            path: path.into(),
+            is_dir,
+            quoted: false,
+            depth: 0,
+            root_len: 0,
+            extra: None,
+        }
     }
-    pub fn exact(path: String) -> Self {
-        Self::Exact(path.into())
+
+    /// Returns the data read with some flags, all `None` if none of them were set.
+    pub fn extra(&self) -> &Extra {
+        self.extra.as_deref().unwrap_or(&NO_EXTRA)
+    }
+
+    /// Bytes taken by the entry, counted by `--limit-memory`.
+    pub fn memory_size(&self) -> usize {
+        let extra = self.extra.as_deref().map(std::mem::size_of_val);
+        std::mem::size_of::<Self>() + self.path.len() + extra.unwrap_or(0)
     }
 }
 
 impl std::fmt::Display for SearchResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Contains(path) => f.write_str(path),
-            Self::Exact(path) => f.write_str(path),
-        }
+        self.entry().fmt(f)
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.path.cmp(&other.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_stays_small() {
+        assert!(std::mem::size_of::<Entry>() <= 40);
+    }
+
+    #[test]
+    fn extra_is_only_allocated_when_set() {
+        let result = SearchResult::exact("./foo".to_owned(), false)
+            .with_inode(None)
+            .with_size(None)
+            .with_match_span(None);
+        assert!(result.entry().extra.is_none());
+
+        let result = result.with_size(Some(10)).with_inode(Some(2));
+        let extra = result.entry().extra();
+        assert_eq!((extra.size, extra.inode), (Some(10), Some(2)));
+        assert_eq!(extra.match_span, None);
     }
 }
@@ -0,0 +1,73 @@
+use std::sync::{Condvar, Mutex};
+
+/// Counting semaphore shared by the search threads, used by `--max-open-files`.
+pub struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until `n` permits are available and takes them all at once, they're given back when the [`Permit`] is dropped.
+    ///
+    /// Taking them at once means a thread never waits while holding part of them, so threads can't wait for each other.
+    pub fn acquire(&self, n: usize) -> Permit<'_> {
+        let available = self.available.lock().unwrap();
+        let mut available = self.released.wait_while(available, |a| *a < n).unwrap();
+        *available -= n;
+        Permit { semaphore: self, n }
+    }
+}
+
+pub struct Permit<'a> {
+    semaphore: &'a Semaphore,
+    n: usize,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.semaphore.available.lock().unwrap() += self.n;
+        self.semaphore.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits_are_given_back_when_dropped() {
+        let semaphore = Semaphore::new(3);
+        let permit = semaphore.acquire(2);
+        assert_eq!(*semaphore.available.lock().unwrap(), 1);
+        drop(permit);
+        assert_eq!(*semaphore.available.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn permits_limit_the_threads_holding_them() {
+        let semaphore = Semaphore::new(2);
+        let holding = Mutex::new((0, 0));
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    let _permit = semaphore.acquire(1);
+                    {
+                        let (now, max) = &mut *holding.lock().unwrap();
+                        *now += 1;
+                        *max = (*max).max(*now);
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    holding.lock().unwrap().0 -= 1;
+                });
+            }
+        });
+        assert_eq!(holding.into_inner().unwrap(), (0, 2));
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Reason an entry was left out of the search, reported with `--report-skipped`.
+///
+/// The summary lists the reasons in the order of this enum.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Skip {
+    Ignored,
+    IgnoreFile,
+    Hidden,
+    Git,
+    DefaultIgnore,
+    ExcludeDir,
+    Type,
+    Affix,
+    Parent,
+    Glob,
+    Extension,
+    Executable,
+    Metadata,
+    Size,
+    Time,
+    Binary,
+    Duplicate,
+}
+
+impl std::fmt::Display for Skip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Skip::Ignored => "ignored with --ignore",
+            Skip::IgnoreFile => "ignored by --ignore-file",
+            Skip::Hidden => "hidden",
+            Skip::Git => "not searched, .git directory (--skip-git)",
+            Skip::DefaultIgnore => "not searched, in the default ignore list",
+            Skip::ExcludeDir => "not searched, excluded with --exclude-dir",
+            Skip::Type => "wrong type (--type)",
+            Skip::Affix => "doesn't match --starts or --ends",
+            Skip::Parent => "parent doesn't match --parent",
+            Skip::Glob => "doesn't match --glob-from",
+            Skip::Extension => "excluded with --exclude-ext",
+            Skip::Executable => "not executable (--path)",
+            Skip::Metadata => "metadata couldn't be read",
+            Skip::Size => "size filter (--min-size, --max-size)",
+            Skip::Time => "time filter (--accessed-*, --mtime-matches, --since-file)",
+            Skip::Binary => "binary filter (--skip-binary, --only-binary)",
+            Skip::Duplicate => "another name of the same file was found (--unique)",
+        })
+    }
+}
+
+/// Entries skipped by the search, kept in a list for each search thread so they don't wait for each other.
+pub struct Skipped {
+    workers: Vec<Mutex<Vec<(PathBuf, Skip)>>>,
+}
+
+impl Skipped {
+    /// Creates the lists for `threads` search threads, plus one for calls from outside of the pool.
+    pub fn new(threads: usize) -> Self {
+        let workers = (0..=threads).map(|_| Mutex::default()).collect();
+        Self { workers }
+    }
+
+    pub fn record(&self, path: &Path, reason: Skip) {
+        let index = rayon::current_thread_index().map_or(0, |i| i + 1);
+        let list = &self.workers[index.min(self.workers.len() - 1)];
+        list.lock().unwrap().push((path.to_owned(), reason));
+    }
+
+    pub fn clear(&self) {
+        for list in &self.workers {
+            list.lock().unwrap().clear();
+        }
+    }
+
+    /// Returns all the skipped entries, sorted by path.
+    pub fn take(&self) -> Vec<(PathBuf, Skip)> {
+        let mut skipped = Vec::new();
+        for list in &self.workers {
+            skipped.append(&mut list.lock().unwrap());
+        }
+        skipped.sort_unstable();
+        skipped
+    }
+}
@@ -1,31 +1,133 @@
 use clap::Parser;
 
-use std::path::PathBuf;
+use std::{
+    io::IsTerminal,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
+};
 
-pub type ResultPath = thin_str::ThinStr;
-pub type ContainsBuf = Vec<ResultPath>;
-pub type ExactBuf = Vec<ResultPath>;
-pub type Buffers = (ExactBuf, ContainsBuf);
+pub type ContainsBuf = Vec<crate::searchresult::Entry>;
+pub type ExactBuf = Vec<crate::searchresult::Entry>;
+pub type SiblingBuf = Vec<crate::searchresult::Entry>;
+pub type Buffers = (ExactBuf, ContainsBuf, SiblingBuf);
+
+/// Directories that are usually huge and rarely contain what's being searched, so they are not traversed by default.
+///
+/// Matched by name, case-sensitively.
+const HARDCODED_IGNORE: phf::Set<&str> = phf::phf_set! {
+    ".git",
+    ".cache",
+    "__pycache__",
+    "Library",
+    "node_modules",
+    "target",
+};
 
 pub struct Search {
     /// If the search must stop when a match is found.
     pub first: bool,
     /// If only exact matches must be accounted for.
     pub exact: bool,
+    /// If the file name without its extension must be compared with the query, instead of the whole name.
+    pub exact_stem: bool,
+    /// Where the query must be in the file name, instead of anywhere.
+    pub anchored: Option<Anchor>,
+    /// Kind of matches that must be found, if only one of them.
+    pub only: Option<Only>,
     /// If all paths should be canonicalized.
     pub canonicalize: bool,
+    /// If only the paths of the results should be canonicalized.
+    pub resolve_output: bool,
     /// If the search is case sensitive.
     pub case_sensitive: bool,
+    /// If an uppercase letter in a query makes it case sensitive, used by `--interactive`.
+    pub smart_case: bool,
     /// If the search is limited to specific directories.
     pub limit: bool,
     /// If the output must be verbose or not.
     pub verbose: bool,
-    /// If hidden directories must be traversed and hidden files counted as matches.
-    pub hidden: bool,
+    /// Entries left out of the current search and why, if they must be reported with `--report-skipped`.
+    pub skipped: Option<crate::skipped::Skipped>,
+    /// If hidden directories must be traversed.
+    pub traverse_hidden: bool,
+    /// If hidden files and directories can be matches.
+    pub match_hidden: bool,
     /// If the select interface will be shown.
     pub select: bool,
     /// If the multiselect interface will be shown.
     pub multiselect: bool,
+    /// Initial filter of the select interfaces.
+    pub select_filter: Option<String>,
+    /// File the results must be written to instead of stdout.
+    pub output_file: Option<std::fs::File>,
+    /// Encoding the results are transcoded to before being written.
+    pub output_encoding: crate::encoding::Encoding,
+    /// Capacity in bytes of the buffer the results are written through, the default of `BufWriter` if not set.
+    pub output_buffer_size: Option<usize>,
+    /// If directories must be printed before or after files.
+    pub group: Group,
+    /// Key the results are sorted by.
+    pub sort: Sort,
+    /// If the sort order must be reversed.
+    pub reverse: bool,
+    /// If the numbers in the paths must be compared by their value when sorting.
+    pub natural_sort: bool,
+    /// If the exact and contains matches must be printed as a single sorted list.
+    pub collapse: bool,
+    /// If the results must always be sorted, even with `-ss`.
+    pub deterministic: bool,
+    /// Number of most recently modified matches that must be printed.
+    pub newest: Option<usize>,
+    /// Number of least recently modified matches that must be printed.
+    pub oldest: Option<usize>,
+    /// If directories must be printed with a trailing separator.
+    pub trailing_slash: bool,
+    /// Character printed instead of the platform path separator.
+    pub path_separator: Option<char>,
+    /// Maximum number of characters of the printed paths, longer ones are shortened in the middle.
+    pub trim: Option<usize>,
+    /// How the paths with special characters must be printed.
+    pub quote_style: QuoteStyle,
+    /// If the depth of the results must be printed before them.
+    pub show_depth: bool,
+    /// If the inode numbers must be printed before the results.
+    pub inode: bool,
+    /// If the type of the results must be printed before them.
+    pub show_type: bool,
+    /// Statuses of the repositories with results, if they must be printed before them with `--git-status`.
+    pub git_status: Option<crate::gitstatus::GitStatus>,
+    /// If the byte offsets of the query in the results must be printed before them.
+    pub show_offsets: bool,
+    /// Directory the results must be printed relative to, and the current directory (needed for relative results).
+    pub relative_to: Option<(PathBuf, PathBuf)>,
+    /// If the results must be printed relative to the search root they were found in.
+    pub relative_roots: bool,
+    /// If the search roots must also be checked against the query.
+    pub match_roots: bool,
+    /// If the other entries of a directory with a match must be added to the results.
+    pub siblings: bool,
+    /// Device and inode numbers of the results found in the current search, if only one name of each file must be kept.
+    pub unique: Option<Mutex<std::collections::HashSet<(u64, u64)>>>,
+    /// Canonicalized directories whose symlinks will be followed.
+    pub follow_under: Vec<PathBuf>,
+    /// Number of levels below a followed symlink that can be searched.
+    pub follow_max_depth: Option<usize>,
+    /// Canonicalized targets of the symlinks followed in the current search, so none is searched twice.
+    pub followed: Mutex<std::collections::HashSet<PathBuf>>,
+    /// Counters reported to stderr while searching, if `--progress` is enabled and stderr is a terminal.
+    pub progress: Option<std::sync::Arc<crate::progress::Progress>>,
+    /// If the results are printed with colors, never when they are written to an `--output-file`.
+    pub colored: bool,
+    /// Colors read from `LS_COLORS`, if `--ls-colors` is enabled and the variable could be parsed.
+    pub ls_colors: Option<crate::lscolors::LsColors>,
+    /// Highlight of the query in the results, from `--color-name` or the `--color-theme`.
+    pub name_color: Highlight,
+    /// Highlight of the `--starts` and `--ends` in the results, from `--color-affix` or the `--color-theme`.
+    pub affix_color: Highlight,
     /// Type of the output.
     ///
     /// Simple makes it not to be highlighted and removes the "Exact:" and "Contains:" distinctions.
@@ -34,18 +136,104 @@ pub struct Search {
     pub output: Output,
     /// Name of the file/folder we're searching.
     pub name: String,
-    /// Pattern the query must start with.
-    pub starts: String,
-    /// Pattern the query must end with.
-    pub ends: String,
+    /// Patterns the query must start with, any of them.
+    pub starts: Vec<String>,
+    /// Patterns the query must end with, any of them.
+    pub ends: Vec<String>,
+    /// Substrings that must all be in the file name, in any order.
+    pub require_all: Vec<memchr::memmem::Finder<'static>>,
+    /// Regex the file name must start with, anchored at its start.
+    pub starts_regex: Option<regex_automata::meta::Regex>,
+    /// Regex the file name must end with, anchored at its end.
+    pub ends_regex: Option<regex_automata::meta::Regex>,
+    /// Name the directory containing the results must have.
+    pub parent: Option<String>,
     /// Type of the query. It can be a File, a Directory or All.
     pub ftype: FileType,
+    /// If only executable files must be found.
+    pub executable: bool,
+    /// Extensions of the files that won't be matched, without the leading dot.
+    pub exclude_ext: Vec<String>,
     /// Directories the user has stated to ignore.
     pub explicit_ignore: Vec<PathBuf>,
-    // /// Directories hard-coded to be ignored.
-    // pub hardcoded_ignore: phf::Set<&'static str>,
-    /// Directories specified by the user to be searched in.
-    pub dirs: Vec<PathBuf>,
+    /// Absolute globs the user has stated to ignore, matched against the canonicalized paths.
+    pub explicit_ignore_globs: Option<globset::GlobSet>,
+    /// Rules loaded from the files given with `--ignore-file`.
+    pub ignore_file: Option<crate::ignorefile::IgnoreFile>,
+    /// Globs loaded with `--glob-from`, one of which the results must match.
+    pub glob_from: Option<crate::ignorefile::IgnoreFile>,
+    /// Directories hard-coded to be ignored, empty if disabled with `--no-default-ignore`.
+    pub hardcoded_ignore: phf::Set<&'static str>,
+    /// If `.git` directories must not be traversed.
+    pub skip_git: bool,
+    /// Names of the directories that are never traversed, wherever they are.
+    pub exclude_dirs: Vec<String>,
+    /// Directories specified by the user to be searched in, with their depth limit.
+    pub dirs: Vec<(PathBuf, Option<usize>)>,
+    /// Paths read with `--files0-from`, checked directly instead of traversing the directories.
+    pub files: Option<Vec<PathBuf>>,
+    /// Only files accessed after this time will be matched.
+    pub accessed_after: Option<SystemTime>,
+    /// Only files accessed before this time will be matched.
+    pub accessed_before: Option<SystemTime>,
+    /// Pattern the formatted modification time of the files must contain.
+    pub mtime_matches: Option<String>,
+    /// strftime format of the modification times checked by `--mtime-matches`.
+    pub time_format: String,
+    /// If the modification times must be formatted in UTC instead of local time.
+    pub time_utc: bool,
+    /// Only files modified after this time will be matched.
+    pub modified_after: Option<SystemTime>,
+    /// Minimum size in bytes of the files that will be found.
+    pub min_size: Option<u64>,
+    /// Maximum size in bytes of the files that will be found.
+    pub max_size: Option<u64>,
+    /// If set, only binary files (true) or only text files (false) will be found.
+    pub binary: Option<bool>,
+    /// Time after which the search is stopped and the results found so far are printed.
+    pub max_time: Option<Duration>,
+    /// If the directories must be searched in the order they are found, instead of depth-first.
+    pub shallow_first: bool,
+    /// Approximate number of bytes the results can take, after which new results are dropped.
+    pub limit_memory: Option<u64>,
+    /// Maximum number of files (everything but directories) that will be kept.
+    pub max_files: Option<usize>,
+    /// Maximum number of directories that will be kept.
+    pub max_dirs: Option<usize>,
+    /// Permits for the directories and files open at the same time, with `--max-open-files`.
+    pub open_files: Option<crate::semaphore::Semaphore>,
+    /// If some results were dropped because of `--limit-memory`.
+    pub truncated: AtomicBool,
+    /// If some directories could not be read, so the results may be incomplete.
+    pub incomplete: AtomicBool,
+    /// If the results must be kept in memory and filtered with the queries read from stdin.
+    pub interactive: bool,
+    /// Number of times the search must be run with `--benchmark`.
+    pub benchmark: Option<usize>,
+    /// Minimum number of results that must be found for the search to succeed.
+    pub min_results: usize,
+    /// If the number of matched files per extension will be printed instead of the results.
+    pub count_by_extension: bool,
+    /// If the sizes of the matched files, summed by directory, will be printed instead of the results.
+    pub disk_usage: bool,
+    /// Number of matches above which only a summary is printed.
+    pub summarize_above: Option<usize>,
+    /// Sum of the sizes of the matched files, if `--total-size` is enabled.
+    pub total_size: Option<AtomicU64>,
+    /// Units of the sizes printed by `--du`, `--total-size` and the memory limit warning.
+    pub size_units: SizeUnits,
+    /// If the results and the search stats must be printed as a single JSON document.
+    pub summary_json: bool,
+    /// Number of directories read, only counted with `--summary-json`.
+    pub scanned_dirs: Option<AtomicUsize>,
+    /// When the search was started, to report the elapsed time in `--summary-json`.
+    pub started: std::time::Instant,
+    /// Number of access times checked, used to detect unreliable access times.
+    pub atime_checked: AtomicUsize,
+    /// Number of access times that were equal to the modification time.
+    pub atime_equals_mtime: AtomicUsize,
+    /// If some creation time couldn't be read for `--sort created`, so the modification time was used.
+    pub created_unavailable: AtomicBool,
 
     /// Memchr Finder
     pub finder: memchr::memmem::Finder<'static>,
@@ -58,20 +246,97 @@ impl Search {
     pub fn new(
         first: bool,
         exact: bool,
+        exact_stem: bool,
+        anchored: Option<Anchor>,
+        only: Option<Only>,
         canonicalize: bool,
+        resolve_output: bool,
         case_sensitive: bool,
+        smart_case: bool,
         limit: bool,
         verbose: bool,
-        hidden: bool,
+        report_skipped: bool,
+        traverse_hidden: bool,
+        match_hidden: bool,
         select: bool,
         multiselect: bool,
+        select_filter: Option<String>,
+        output_file: Option<PathBuf>,
+        append: bool,
+        output_encoding: crate::encoding::Encoding,
+        output_buffer_size: Option<usize>,
+        group: Group,
+        sort: Sort,
+        reverse: bool,
+        natural_sort: bool,
+        collapse: bool,
+        deterministic: bool,
+        newest: Option<usize>,
+        oldest: Option<usize>,
+        trailing_slash: bool,
+        path_separator: Option<char>,
+        trim: Option<usize>,
+        quote_style: Option<QuoteStyle>,
+        show_depth: bool,
+        inode: bool,
+        show_type: bool,
+        git_status: bool,
+        show_offsets: bool,
+        relative_to: Option<PathBuf>,
+        relative_roots: bool,
+        match_roots: bool,
+        siblings: bool,
+        unique: bool,
+        follow_under: Vec<PathBuf>,
+        follow_max_depth: Option<usize>,
+        progress: bool,
+        ls_colors: bool,
+        name_color: Highlight,
+        affix_color: Highlight,
         output: u8,
         name: String,
-        starts: String,
-        ends: String,
+        starts: Vec<String>,
+        ends: Vec<String>,
+        require_all: Vec<String>,
+        starts_regex: Option<regex_automata::meta::Regex>,
+        ends_regex: Option<regex_automata::meta::Regex>,
+        parent: Option<String>,
         ftype: FileType,
+        executable: bool,
+        exclude_ext: Vec<String>,
         explicit_ignore: Vec<PathBuf>,
-        search_in_dirs: Vec<PathBuf>,
+        explicit_ignore_globs: Option<globset::GlobSet>,
+        ignore_file: Option<crate::ignorefile::IgnoreFile>,
+        glob_from: Option<crate::ignorefile::IgnoreFile>,
+        default_ignore: bool,
+        skip_git: bool,
+        exclude_dirs: Vec<String>,
+        search_in_dirs: Vec<(PathBuf, Option<usize>)>,
+        files: Option<Vec<PathBuf>>,
+        accessed_within: Option<Duration>,
+        accessed_before: Option<Duration>,
+        mtime_matches: Option<String>,
+        time_format: String,
+        time_utc: bool,
+        modified_after: Option<SystemTime>,
+        min_size: Option<u64>,
+        max_size: Option<u64>,
+        binary: Option<bool>,
+        min_results: usize,
+        count_by_extension: bool,
+        disk_usage: bool,
+        summarize_above: Option<usize>,
+        total_size: bool,
+        size_units: SizeUnits,
+        summary_json: bool,
+        max_time: Option<Duration>,
+        shallow_first: bool,
+        limit_memory: Option<u64>,
+        max_files: Option<usize>,
+        max_dirs: Option<usize>,
+        max_open_files: Option<usize>,
+        interactive: bool,
+        benchmark: Option<usize>,
     ) -> Search {
         let output = match output {
             0 => Output::Normal,
@@ -84,24 +349,178 @@ impl Search {
             .ok()
             .flatten()
             .unwrap_or(usize::MAX);
+        let output_file = output_file.map(|path| {
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(append)
+                .truncate(!append)
+                .open(&path);
+            match file {
+                Ok(file) => file,
+                Err(e) => {
+                    eprintln!("Error: Could not open output file {:?}: {e}", path);
+                    std::process::exit(1)
+                }
+            }
+        });
+        // Escape control characters by default when printing to a terminal, so file names can't mess with it
+        let quote_style = quote_style.unwrap_or_else(|| {
+            if output_file.is_none() && std::io::stdout().is_terminal() {
+                QuoteStyle::C
+            } else {
+                QuoteStyle::Literal
+            }
+        });
+        let now = SystemTime::now();
+        let ago = |d: Duration| now.checked_sub(d).unwrap_or(SystemTime::UNIX_EPOCH);
+        let relative_to = relative_to.map(|base| {
+            let Ok(base) = base.canonicalize() else {
+                eprintln!("Error: The {:?} directory does not exist", base);
+                std::process::exit(1)
+            };
+            let current_dir = std::env::current_dir().expect("Could not read current directory");
+            (base, current_dir)
+        });
+        let threads = rayon::current_num_threads();
+        let skipped = report_skipped.then(|| crate::skipped::Skipped::new(threads));
+        let follow_under = follow_under
+            .into_iter()
+            .map(|dir| {
+                dir.canonicalize().unwrap_or_else(|_| {
+                    eprintln!("Error: The {:?} directory does not exist", dir);
+                    std::process::exit(1)
+                })
+            })
+            .collect();
+        #[cfg(not(any(unix, windows)))]
+        if inode && verbose {
+            eprintln!("Warning: Inode numbers are not available on this platform, '-' will be printed instead");
+        }
+        let progress = (progress && std::io::stderr().is_terminal()).then(Default::default);
+        // Don't write color codes to the file
+        let colored = output_file.is_none() && colored::control::SHOULD_COLORIZE.should_colorize();
+        let ls_colors = if ls_colors {
+            let colors = crate::lscolors::LsColors::from_env();
+            if colors.is_none() && verbose {
+                eprintln!(
+                    "LS_COLORS is not set or could not be parsed, results will not be colored"
+                );
+            }
+            colors
+        } else {
+            None
+        };
 
         Search {
             first,
             exact,
+            exact_stem,
+            anchored,
+            only,
             canonicalize,
+            resolve_output,
             case_sensitive,
+            smart_case,
             limit,
             verbose,
-            hidden,
+            skipped,
+            traverse_hidden,
+            match_hidden,
             select,
             multiselect,
+            select_filter,
+            output_file,
+            output_encoding,
+            output_buffer_size,
+            group,
+            sort,
+            reverse,
+            natural_sort,
+            collapse,
+            deterministic,
+            newest,
+            oldest,
+            trailing_slash,
+            path_separator,
+            trim,
+            quote_style,
+            show_depth,
+            inode,
+            show_type,
+            git_status: git_status.then(|| crate::gitstatus::GitStatus::new(verbose)),
+            show_offsets,
+            relative_to,
+            relative_roots,
+            match_roots,
+            siblings,
+            unique: unique.then(Default::default),
+            follow_under,
+            follow_max_depth,
+            followed: Default::default(),
+            progress,
+            colored,
+            ls_colors,
+            name_color,
+            affix_color,
             output,
             name,
             starts,
             ends,
+            require_all: require_all
+                .iter()
+                .map(|s| memchr::memmem::Finder::new(s.as_bytes()).into_owned())
+                .collect(),
+            starts_regex,
+            ends_regex,
+            parent,
             ftype,
+            executable,
+            exclude_ext,
             explicit_ignore,
+            explicit_ignore_globs,
+            ignore_file,
+            glob_from,
+            hardcoded_ignore: if default_ignore && !traverse_hidden {
+                HARDCODED_IGNORE
+            } else {
+                phf::phf_set! {}
+            },
+            skip_git,
+            exclude_dirs,
             dirs: search_in_dirs,
+            files,
+            accessed_after: accessed_within.map(ago),
+            accessed_before: accessed_before.map(ago),
+            mtime_matches,
+            time_format,
+            time_utc,
+            modified_after,
+            min_size,
+            max_size,
+            binary,
+            min_results,
+            count_by_extension,
+            disk_usage,
+            summarize_above,
+            total_size: total_size.then(|| AtomicU64::new(0)),
+            size_units,
+            summary_json,
+            scanned_dirs: summary_json.then(|| AtomicUsize::new(0)),
+            started: std::time::Instant::now(),
+            max_time,
+            shallow_first,
+            limit_memory,
+            max_files,
+            max_dirs,
+            open_files: max_open_files.map(crate::semaphore::Semaphore::new),
+            truncated: AtomicBool::new(false),
+            incomplete: AtomicBool::new(false),
+            interactive,
+            benchmark,
+            atime_checked: AtomicUsize::new(0),
+            atime_equals_mtime: AtomicUsize::new(0),
+            created_unavailable: AtomicBool::new(false),
 
             finder,
             max_depth,
@@ -116,10 +535,103 @@ pub enum Output {
     SuperSimple,
 }
 
+#[derive(PartialEq, Clone, Copy)]
+pub enum Group {
+    Mixed,
+    DirsFirst,
+    FilesFirst,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Sort {
+    /// Sort by path
+    Name,
+    /// Sort by the depth below the search directory, shallowest first
+    Depth,
+    /// Sort by the last access time, oldest first
+    Accessed,
+    /// Sort by the creation time, oldest first. The modification time is used where it's not available
+    Created,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum FsCase {
+    /// Case-insensitive on Windows, smart case on other platforms
+    Auto,
+    /// Names that only differ in case are the same file, so searches are case-insensitive
+    Insensitive,
+    /// Names that only differ in case are different files, so searches are case-sensitive
+    Sensitive,
+}
+
+/// Preset highlight colors, set with `--color-theme`.
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Theme {
+    /// Bright red query and bright magenta '--starts' and '--ends', for dark backgrounds
+    Dark,
+    /// Red query and blue '--starts' and '--ends', which are still readable on light backgrounds
+    Light,
+    /// No colors, the highlighted parts are only bold
+    Mono,
+}
+
+impl Theme {
+    /// Highlights of the query and of the '--starts' and '--ends'.
+    fn highlights(self) -> (Highlight, Highlight) {
+        use colored::Color;
+        match self {
+            Theme::Dark => (
+                Highlight::Color(Color::BrightRed),
+                Highlight::Color(Color::BrightMagenta),
+            ),
+            Theme::Light => (Highlight::Color(Color::Red), Highlight::Color(Color::Blue)),
+            Theme::Mono => (Highlight::Bold, Highlight::Bold),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Anchor {
+    /// The file name must start with the query
+    Start,
+    /// The file name must end with the query
+    End,
+    /// The file name must be the query
+    Both,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Only {
+    /// Only the files named exactly as the query
+    Exact,
+    /// Only the files that contain the query, but are not exactly it
+    Contains,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum QuoteStyle {
+    /// Print the paths as they are
+    Literal,
+    /// Quote the paths with special characters so they can be pasted in a shell
+    Shell,
+    /// Escape the control characters like C does, e.g. '\n' or '\x1b'
+    C,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum SizeUnits {
+    /// Powers of 1024, e.g. "1.5 KiB", the same units of '--min-size' and '--max-size'
+    #[default]
+    Binary,
+    /// Powers of 1000, e.g. "1.5 kB"
+    Decimal,
+}
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum FileType {
     Dir,
     File,
+    Symlink,
     All,
 }
 
@@ -129,8 +641,9 @@ impl From<Option<String>> for FileType {
             match s.as_str() {
                 "d" => FileType::Dir,
                 "f" => FileType::File,
+                "l" => FileType::Symlink,
                 _ => {
-                    eprintln!("File type {} not recognized\nPlease use 'f' for files and 'd' for directories\nSee --help for more information\n", s);
+                    eprintln!("File type {} not recognized\nPlease use 'f' for files, 'd' for directories and 'l' for symlinks\nSee --help for more information\n", s);
                     std::process::exit(1)
                 }
             }
@@ -162,7 +675,8 @@ fn styles() -> clap::builder::Styles {
     name = "Hunt",
     about = "Simple command to search a file/folder by name on the current directory.\nBy default it searches all occurrences.",
     version,
-    styles = styles()
+    styles = styles(),
+    after_help = "Exit status:\n  0  The search was complete and something was found\n  1  The search was complete but nothing was found, or an error happened\n  2  The search was incomplete (unreadable directories, '--max-time' or '--limit-memory'), regardless of the matches"
 )]
 pub struct Cli {
     /// Stop when first occurrence is found
@@ -175,16 +689,60 @@ pub struct Cli {
     #[arg(short, long)]
     exact: bool,
 
+    /// Only search for occurrences whose name without the extension is exactly the query
+    ///
+    /// e.g. if query is "SomeFile", "SomeFile.txt" will be found, but "SomeFile2.txt" will be skipped.
+    /// Only the last extension is removed, so "SomeFile.tar.gz" will be skipped too
+    #[arg(long, conflicts_with = "same_name_as")]
+    exact_stem: bool,
+
+    /// Only find occurrences that start with the query, end with it or that are exactly it ('both')
+    ///
+    /// e.g. with '--anchored start', "SomeFile" finds "SomeFile.txt" but not "I'mSomeFile".
+    /// '--exact' behaves like '--anchored both'
+    #[arg(long, value_name = "ANCHOR")]
+    anchored: Option<Anchor>,
+
+    /// Only find the exact matches, like '--exact', or only the files that contain the query without being exactly it
+    #[arg(long, value_name = "KIND", visible_alias = "only-exact-or-contains")]
+    only: Option<Only>,
+
     /// If enabled, all paths will be canonicalized.
     #[arg(short, long)]
     canonicalize: bool,
 
+    /// If enabled, the paths of the results will be canonicalized, resolving all symlinks
+    ///
+    /// Unlike '--canonicalize', the search itself is done with the given paths, so it's faster.
+    /// Results that can't be canonicalized (e.g. broken symlinks) are printed as found, which is reported with '--verbose'
+    #[arg(long, visible_alias = "canonicalize-results")]
+    resolve_output: bool,
+
     /// If enabled, the search will be case-sensitive
     ///
-    /// Note that case-sensitivity will be activated automatically when the search query contains an uppercase letter
-    #[arg(short = 'C', long)]
+    /// Note that case-sensitivity will be activated automatically when the search query, '--starts' or '--ends' contain an uppercase letter, unless '--no-smart-case' is used
+    #[arg(short = 'C', long, overrides_with = "ignore_case")]
     case_sensitive: bool,
 
+    /// If enabled, the search will be case-insensitive, even if the query contains an uppercase letter
+    #[arg(short = 'I', long, overrides_with = "case_sensitive")]
+    ignore_case: bool,
+
+    /// Make the search case-sensitive when the query, '--starts' or '--ends' contain an uppercase letter (the default)
+    #[arg(long, overrides_with = "no_smart_case")]
+    smart_case: bool,
+
+    /// Only use '--case-sensitive' and '--ignore-case' to decide the case sensitivity, never the query
+    #[arg(long, overrides_with = "smart_case")]
+    no_smart_case: bool,
+
+    /// Case sensitivity of the filesystem, used when neither '--case-sensitive' nor '--ignore-case' are set
+    ///
+    /// With 'auto', searches are case-insensitive on Windows, whose paths are, and use smart case elsewhere.
+    /// '--smart-case' can still be used to enable smart case on case-insensitive filesystems
+    #[arg(long, value_name = "MODE", default_value = "auto")]
+    fs_case: FsCase,
+
     /// Print verbose output
     ///
     /// It'll show all errors found:    
@@ -192,6 +750,19 @@ pub struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Skip the search directories that don't exist instead of exiting with an error, like 'grep -s'
+    ///
+    /// Useful in scripts with globbed directories that may not exist. It's still an error if none of them exist
+    #[arg(long, conflicts_with = "verbose")]
+    no_messages: bool,
+
+    /// When the search is finished, print to stderr the entries that were left out and why, with a count for each reason
+    ///
+    /// e.g. the hidden or ignored entries, the ones of the wrong type or the ones filtered by size or time.
+    /// Filters like '--type' or '--min-size' are only reported for the entries whose name matches the query
+    #[arg(long)]
+    report_skipped: bool,
+
     /// Prints without formatting (without "Contains:" and "Exact:")
     ///
     /// -ss Output is not sorted
@@ -200,36 +771,318 @@ pub struct Cli {
 
     /// If enabled, it searches inside hidden directories
     ///
-    /// If not enabled, hidden directories will be skipped
+    /// If not enabled, hidden directories will be skipped. '.git' directories are still skipped unless '--no-skip-git' is used
     #[arg(short = 'H', long)]
     hidden: bool,
 
+    /// Search inside hidden directories, without finding the hidden files and directories themselves
+    #[arg(long)]
+    traverse_hidden: bool,
+
+    /// Find the hidden files and directories, without searching inside the hidden directories
+    #[arg(long)]
+    match_hidden: bool,
+
     /// When the search is finished, choose one file between the results
     ///
     /// The selected file will be printed as if -ss was used
+    ///
+    /// Type to filter the results by their path, use the arrows to move and Enter to select
     #[arg(long, conflicts_with_all(["simple", "multiselect", "first"]))]
     select: bool,
 
     /// When the search is finished, choose between the results
     ///
     /// The selected files will be printed one after the other, separated by spaces
+    ///
+    /// Type to filter the results by their path, use the arrows to move, Space to toggle a file and Enter to confirm
     #[arg(long, conflicts_with_all(["simple", "select", "first"]))]
     multiselect: bool,
 
+    /// Initial filter text for '--select' and '--multiselect'
+    #[arg(long, value_name = "INITIAL")]
+    select_filter: Option<String>,
+
+    /// Write the results to this file instead of stdout
+    ///
+    /// The file is truncated unless '--append' is used.
+    /// With '--select' or '--multiselect', the interface is still shown in the terminal and the selection is written to the file
+    #[arg(short, long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+
+    /// Append the results to the '--output-file' instead of truncating it
+    #[arg(long, requires = "output_file")]
+    append: bool,
+
+    /// Encoding of the printed results, for terminals that don't support UTF-8 like some legacy Windows consoles
+    ///
+    /// Characters that can't be encoded are replaced with '?', which is reported with '--verbose'
+    #[arg(long, value_name = "ENCODING", default_value = "utf-8")]
+    output_encoding: crate::encoding::Encoding,
+
+    /// Size of the buffer the results are written through, e.g. '1M'
+    ///
+    /// Bigger buffers need fewer writes when printing many results. The size is a number followed by K, M or G (powers of 1024), or a number of bytes
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    output_buffer_size: Option<u64>,
+
+    /// Print directories before files
+    #[arg(long, conflicts_with = "files_first")]
+    dirs_first: bool,
+
+    /// Print files before directories
+    #[arg(long)]
+    files_first: bool,
+
+    /// Sort the results by this key, results with the same key are sorted by path
+    #[arg(long, value_name = "KEY", default_value = "name")]
+    sort: Sort,
+
+    /// Reverse the sort order
+    #[arg(short = 'R', long)]
+    reverse: bool,
+
+    /// Compare the numbers in the paths by their value, so "file2" is sorted before "file10"
+    #[arg(long, visible_alias = "sort-by-name-natural")]
+    natural_sort: bool,
+
+    /// Print the exact and contains matches as a single sorted list, without the "Contains:" and "Exact:" headers
+    ///
+    /// Unlike '--simple', the matches are still highlighted and the whole list is sorted, instead of the two lists one after the other
+    #[arg(long)]
+    collapse: bool,
+
+    /// Always print the results in the same order, so the output of a search can be compared between runs
+    ///
+    /// The results of '-ss' are sorted too, so they are printed when the search finishes
+    #[arg(long, conflicts_with = "first")]
+    deterministic: bool,
+
+    /// Only print the N most recently modified matches, newest first
+    ///
+    /// The modification time of every match is read, and the matches are printed as a single list
+    #[arg(long, value_name = "N", conflicts_with_all(["oldest", "first", "siblings", "simple"]))]
+    newest: Option<usize>,
+
+    /// Only print the N least recently modified matches, oldest first
+    ///
+    /// The modification time of every match is read, and the matches are printed as a single list
+    #[arg(long, value_name = "N", conflicts_with_all(["first", "siblings", "simple"]))]
+    oldest: Option<usize>,
+
+    /// Print directories with a trailing path separator
+    ///
+    /// e.g. "/home/user/Downloads/"
+    #[arg(long)]
+    trailing_slash: bool,
+
+    /// Print this character instead of the platform path separator
+    ///
+    /// Only changes the output, e.g. '--path-separator /' prints "C:/Users/user" on Windows
+    #[arg(long, value_name = "CHAR")]
+    path_separator: Option<char>,
+
+    /// Shorten the printed paths longer than N characters, replacing their middle with '...'
+    ///
+    /// The file name is always kept, e.g. "/home/.../file", if it doesn't fit the whole top of the path is replaced instead.
+    /// N must be at least 8. With 'auto' the width of the terminal is used,
+    /// and nothing is shortened if the results are not printed to a terminal
+    #[arg(
+        long,
+        visible_alias = "trim-output-width",
+        value_name = "N|auto",
+        value_parser = parse_width,
+        conflicts_with = "show_offsets"
+    )]
+    trim: Option<Width>,
+
+    /// How to print the paths that contain special characters
+    ///
+    /// Defaults to 'c' when printing to a terminal, so file names with control characters can't corrupt it, and to 'literal' otherwise
+    #[arg(long, value_name = "STYLE")]
+    quote_style: Option<QuoteStyle>,
+
+    /// Print the depth of each result below its search directory before its path
+    ///
+    /// The entries of the search directory are at depth 1, the same depth used by the ':DEPTH' limit of the search directories.
+    /// With '--match-roots', the search directories themselves are at depth 0
+    #[arg(long, conflicts_with_all(["select", "multiselect"]))]
+    show_depth: bool,
+
+    /// Print the inode number of each result before its path (the file index on Windows)
+    ///
+    /// Useful for spotting hard links. '-' is printed when it can't be read
+    #[arg(long, conflicts_with_all(["select", "multiselect"]))]
+    inode: bool,
+
+    /// Print the type of each result before its path, like the first column of 'ls -l'
+    ///
+    /// 'f' -> file | 'd' -> directory | 'l' -> symlink | 'p' -> named pipe | 's' -> socket | 'b' -> block device | 'c' -> character device.
+    /// '?' is printed when it can't be read
+    #[arg(long, visible_alias = "print-type", conflicts_with_all(["select", "multiselect"]))]
+    show_type: bool,
+
+    /// Print the git status of each result before its path, like 'git status --short'
+    ///
+    /// 'git status' runs once for each repository with results. Files without changes and the ones outside of a repository get a blank status
+    #[arg(long, visible_alias = "print-git-status", conflicts_with_all(["select", "multiselect"]))]
+    git_status: bool,
+
+    /// Print the byte offsets of the query in each result as 'START:END:PATH', implies '--simple'
+    ///
+    /// The offsets are into the whole printed path, e.g. "4:8:src/main.rs" for "main".
+    /// Paths are never quoted with this flag, so the offsets always match
+    #[arg(
+        long,
+        visible_alias = "print-match-offsets",
+        conflicts_with_all(["select", "multiselect", "siblings", "count_by_extension"])
+    )]
+    show_offsets: bool,
+
+    /// Print the results relative to this directory
+    ///
+    /// e.g. "hunt --relative-to /home/user/Downloads file /home/user/Documents" prints "../Documents/file"
+    #[arg(long, value_name = "DIR")]
+    relative_to: Option<PathBuf>,
+
+    /// Print each result relative to the search directory it was found in
+    ///
+    /// e.g. "hunt --relative-roots file ~/Documents ~/Downloads" prints "notes/file" instead of "/home/user/Documents/notes/file".
+    /// When search directories are nested, a result is printed relative to the directory whose search found it
+    #[arg(
+        long,
+        visible_alias = "relative-root-auto",
+        conflicts_with = "relative_to"
+    )]
+    relative_roots: bool,
+
+    /// Also check the directories where the search starts against the query
+    ///
+    /// e.g. "hunt --match-roots project /home/user/project" will include "/home/user/project" in the results
+    #[arg(long)]
+    match_roots: bool,
+
+    /// Also list the other files and directories that are next to a match
+    ///
+    /// They will be shown in a separate "Siblings:" section
+    #[arg(long)]
+    siblings: bool,
+
+    /// Only keep one result for each file, collapsing its hard links and the paths found from overlapping search directories
+    ///
+    /// Files are compared by their device and inode numbers (the volume and file index on Windows).
+    /// Which of the names is kept depends on the order they are found in, so it can change between runs
+    #[arg(long, visible_alias = "dedup-by-inode")]
+    unique: bool,
+
+    /// Follow the symlinks to directories that are inside this directory
+    ///
+    /// Other symlinks are not searched, so stray links can't lead the search into system directories.
+    /// Each directory is searched only once, and links to a directory that contains them are skipped
+    ///
+    /// Can be used multiple times
+    #[arg(long, value_name = "DIR")]
+    follow_under: Vec<PathBuf>,
+
+    /// Only search N levels below each followed symlink, 1 meaning only its direct entries
+    ///
+    /// Applies in addition to the depth limit of the search directory, the stricter of both is used.
+    /// A followed link inside another one starts a new count, but can't go deeper than the outer limit
+    #[arg(long, value_name = "N", requires = "follow_under")]
+    follow_max_depth: Option<usize>,
+
+    /// Show the number of scanned directories and found matches while searching
+    ///
+    /// Only shown when stderr is a terminal
+    #[arg(long)]
+    progress: bool,
+
+    /// Color the file names of the results like `ls` does, using the LS_COLORS environment variable
+    ///
+    /// The matched parts of the names are still highlighted
+    #[arg(long)]
+    ls_colors: bool,
+
+    /// Preset colors used to highlight the matches, for dark or light terminal backgrounds, or only bold text
+    ///
+    /// '--color-name' and '--color-affix' override the colors of the theme
+    #[arg(
+        long,
+        value_name = "THEME",
+        default_value = "dark",
+        visible_alias = "theme"
+    )]
+    color_theme: Theme,
+
+    /// Color used to highlight the query in the results, 'bold' to only make it bold or 'none' to not highlight it
+    ///
+    /// One of black, red, green, yellow, blue, magenta, cyan and white, optionally prefixed by 'bright-' (e.g. 'bright-blue').
+    /// By default, the color of the '--color-theme'
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    color_name: Option<Highlight>,
+
+    /// Color used to highlight the '--starts' and '--ends' in the results, 'bold' or 'none'
+    ///
+    /// Accepts the same colors as '--color-name'. By default, the color of the '--color-theme'
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    color_affix: Option<Highlight>,
+
     /// Only files that start with this will be found
+    ///
+    /// Can be used multiple times, files that start with any of them will be found
     #[arg(short = 'S', long = "starts")]
-    starts_with: Option<String>,
+    starts_with: Vec<String>,
+
+    /// Only files whose name starts with a match of this regex will be found
+    ///
+    /// It's checked in addition to '--starts', and uses the case sensitivity of the query, without enabling smart case.
+    /// e.g. "hunt --starts-regex 'IMG_[0-9]+' ''"
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex)]
+    starts_regex: Option<String>,
 
     /// Only files that end with this will be found
+    ///
+    /// Can be used multiple times, files that end with any of them will be found
+    ///
+    /// e.g. "hunt --ends .jpg --ends .png"
     #[arg(short = 'E', long = "ends")]
-    ends_with: Option<String>,
+    ends_with: Vec<String>,
+
+    /// Only files whose name ends with a match of this regex will be found
+    ///
+    /// It's checked in addition to '--ends', and uses the case sensitivity of the query, without enabling smart case.
+    /// e.g. "hunt --ends-regex '\.(jpe?g|png)' ''"
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex)]
+    ends_regex: Option<String>,
+
+    /// Only files that contain all these substrings, in any order, will be found. The format is: '--require-all str1,str2,...'
+    ///
+    /// e.g. "hunt --require-all test,integration" finds "integration_test.rs" and "test_integration.py".
+    /// All the files found are treated as only containing the query, never as exact matches
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    require_all: Vec<String>,
+
+    /// Only files directly inside a directory named NAME will be found
+    ///
+    /// e.g. "hunt --parent migrations -e up.sql" finds every "up.sql" inside a "migrations" directory.
+    /// The name is compared with the same case sensitivity as the query
+    #[arg(long, value_name = "NAME")]
+    parent: Option<String>,
 
     /// Specifies the type of the file
     ///
-    /// 'f' -> file | 'd' -> directory
+    /// 'f' -> file | 'd' -> directory | 'l' -> symlink
+    ///
+    /// Symlinks are also files, and they are never traversed unless they point inside a '--follow-under' directory
     #[arg(short = 't', long = "type")]
     file_type: Option<String>,
 
+    /// Files with these extensions won't be found, the format is: '--exclude-ext tmp,bak,...'
+    ///
+    /// The comparison is case-insensitive. Directories are not affected
+    #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+    exclude_ext: Vec<String>,
+
     /// Ignores the provided files/directories. 
     /// The format is: '-i dir1,dir2,dir3,...'
     /// 
@@ -238,10 +1091,315 @@ pub struct Cli {
     ///   Examples: '/home/user/Downloads' or './Downloads'
     /// - If only a name is provided, ALL matching files/directories will be ignored
     ///   Examples: 'file.txt' or 'node_modules'
+    /// - If the path is absolute and contains '*', '?' or '[', it's a glob, and
+    ///   all the matching paths will be ignored ('**' matches multiple directories)
+    ///   Examples: '/home/*/.cache' or '/mnt/**/build'
     #[arg(short = 'i', long = "ignore", value_delimiter = ',', verbatim_doc_comment)]
     ignore: Option<Vec<PathBuf>>,
 
+    /// Ignores the files/directories matching the patterns in this file
+    ///
+    /// The syntax is similar to '.gitignore': one glob per line, '#' for comments, '!' to negate a pattern and a trailing '/' to only match directories.
+    /// Patterns without a '/' are matched against the file name, the others against the whole path.
+    ///
+    /// Can be used multiple times, later patterns take precedence.
+    #[arg(long, value_name = "PATH")]
+    ignore_file: Vec<PathBuf>,
+
+    /// Only files matching one of the globs in this file will be found, e.g. a list of '*.tmp' and '*.bak' to clean up
+    ///
+    /// Uses the same syntax as '--ignore-file': one glob per line, '#' for comments, and '!' to exclude the files matched by the previous globs.
+    /// The globs are case-sensitive and checked in addition to the query. Can be used multiple times
+    #[arg(long, value_name = "PATH")]
+    glob_from: Vec<PathBuf>,
+
+    /// Let '*' and '?' match '/' in the globs of '--ignore', '--ignore-file' and '--glob-from'
+    ///
+    /// e.g. '/home/*/target' then also ignores '/home/user/project/target', as '**' does by default.
+    /// Globs matched against the file name are not affected. Loose globs can match more paths, but are not slower to check
+    #[arg(long)]
+    no_literal_separator: bool,
+
+    /// Don't traverse the directories named NAME, anywhere in the tree
+    ///
+    /// Only the name is compared, so files named NAME are still found, and so are the directories themselves, only their contents are skipped.
+    /// Unlike '--ignore', paths are never resolved. Can be used multiple times, e.g. '--exclude-dir build --exclude-dir dist'
+    #[arg(long, value_name = "NAME")]
+    exclude_dir: Vec<String>,
+
+    /// Also traverse the directories skipped by default: '.git', '.cache', '__pycache__', 'Library', 'node_modules' and 'target'
+    ///
+    /// These directories can still be found, only their contents are skipped. They are always searched when given as a search directory or with '--hidden'.
+    /// With '--hidden', '.git' is still skipped unless '--no-skip-git' is used
+    #[arg(long)]
+    no_default_ignore: bool,
+
+    /// Don't traverse '.git' directories, even with '--hidden' (the default)
+    ///
+    /// Only the '.git' directories themselves are skipped, not the files ignored by git. They are still searched when given as a search directory
+    #[arg(long, overrides_with = "no_skip_git")]
+    skip_git: bool,
+
+    /// Traverse '.git' directories when searching hidden directories
+    #[arg(long, overrides_with = "skip_git")]
+    no_skip_git: bool,
+
+    /// Only files accessed within this duration will be found
+    ///
+    /// The format is a number followed by a unit: s, m, h, d or w (e.g. '30m', '2d')
+    ///
+    /// Note that many filesystems are mounted with 'noatime' or 'relatime', which makes access times unreliable
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    accessed_within: Option<Duration>,
+
+    /// Only files not accessed within this duration will be found
+    ///
+    /// Uses the same format as '--accessed-within'
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    accessed_before: Option<Duration>,
+
+    /// Only files whose modification time contains this pattern will be found
+    ///
+    /// The time is formatted in local time with '--time-format', by default as '%Y-%m-%d %H:%M', e.g. "2023-11-05 17:30"
+    ///
+    /// e.g. "hunt --mtime-matches 2023-11 ''" finds all files modified in November 2023
+    #[arg(long, value_name = "PATTERN")]
+    mtime_matches: Option<String>,
+
+    /// strftime format the modification times are formatted with for '--mtime-matches'
+    ///
+    /// e.g. "hunt --mtime-matches Sun --time-format %a ''" finds all files modified on a Sunday
+    #[arg(
+        long,
+        value_name = "FMT",
+        default_value = "%Y-%m-%d %H:%M",
+        value_parser = parse_time_format,
+        requires = "mtime_matches"
+    )]
+    time_format: String,
+
+    /// Format the modification times for '--mtime-matches' in UTC instead of local time
+    #[arg(long, requires = "mtime_matches")]
+    time_utc: bool,
+
+    /// Only files modified after this reference file will be found
+    ///
+    /// The modification time of PATH is read once, following symlinks, and only strictly newer files are kept.
+    /// e.g. "touch .built" after a build, then "hunt --since-file .built ''" lists what changed since
+    #[arg(long, value_name = "PATH")]
+    since_file: Option<PathBuf>,
+
+    /// Only files of at least this size will be found, e.g. '1k' or '500M'
+    ///
+    /// The size is a number followed by K, M or G (powers of 1024), or a number of bytes. Directories are never found
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Only files of at most this size will be found, e.g. '1k' or '500M'
+    ///
+    /// Uses the same format as '--min-size', and both can be combined. Directories are never found
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Skip the binary files, only text files will be found
+    ///
+    /// A file is binary if its first 8 KiB contain a NUL byte, so empty files are text. Directories are never found
+    #[arg(long, conflicts_with = "only_binary")]
+    skip_binary: bool,
+
+    /// Only binary files will be found, the opposite of '--skip-binary'
+    #[arg(long)]
+    only_binary: bool,
+
+    /// Exit with an error if less than this number of results are found
+    ///
+    /// Useful for checking that some files exist, e.g. "hunt -ss --min-results 1 Cargo.toml"
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "first")]
+    min_results: usize,
+
+    /// Print how many files were found for each extension, sorted by count, instead of the results
+    ///
+    /// Directories are not counted. Files without an extension are grouped under '(none)'
+    ///
+    /// e.g. "hunt --count-by-extension --type f '' src"
+    #[arg(long, conflicts_with_all(["first", "simple", "select", "multiselect"]))]
+    count_by_extension: bool,
+
+    /// Print the combined size of the matched files in each directory, like 'du', instead of the results
+    ///
+    /// Sizes are added to every directory up to the search directory, and printed as 'SIZE<TAB>DIR', biggest first.
+    /// Directories themselves are not counted, only the files found by the search
+    ///
+    /// e.g. "hunt --du --ends .mp4 '' ~/" shows where the videos take the most space
+    #[arg(
+        long,
+        visible_alias = "print-size-tree",
+        conflicts_with_all(["first", "simple", "select", "multiselect", "count_by_extension"])
+    )]
+    du: bool,
+
+    /// If more than N matches are found, print how many there are and the directories with most of them instead of the results
+    ///
+    /// Avoids flooding the terminal with accidentally broad searches
+    #[arg(
+        long,
+        value_name = "N",
+        visible_alias = "match-count-threshold",
+        conflicts_with_all(["first", "simple", "select", "multiselect", "count_by_extension", "newest", "oldest"])
+    )]
+    summarize_above: Option<usize>,
+
+    /// Print the combined size of all the matched files after the results
+    ///
+    /// Directories are not counted, only the files found by the search
+    #[arg(long, conflicts_with_all(["first", "select", "multiselect"]))]
+    total_size: bool,
+
+    /// Units of the sizes printed by '--du', '--total-size' and the '--limit-memory' warning
+    ///
+    /// Defaults to 'binary', the units of '--min-size' and '--max-size'
+    #[arg(long, value_name = "UNITS", default_value = "binary")]
+    size_units: SizeUnits,
+
+    /// Print a single JSON document with the results and the stats of the search
+    ///
+    /// e.g. '{"results":[{"path":"./a","kind":"exact","is_dir":false}],"stats":{"scanned_dirs":1,"elapsed_ms":0.2,"exact":1,"contains":0,"siblings":0}}'.
+    /// With '--total-size', the stats also contain the "total_size" in bytes
+    #[arg(
+        long,
+        conflicts_with_all([
+            "first",
+            "simple",
+            "select",
+            "multiselect",
+            "count_by_extension",
+            "summarize_above",
+            "interactive",
+            "benchmark",
+        ])
+    )]
+    summary_json: bool,
+
+    /// Stop the search after this duration and print the results found so far
+    ///
+    /// Uses the same format as '--accessed-within'. If the search times out, hunt exits with code 2
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with = "benchmark")]
+    max_time: Option<Duration>,
+
+    /// Search the directories closer to the search roots first, to find the shallow matches sooner
+    ///
+    /// Useful with '--first' or '--max-time' when the expected match is not deeply nested
+    #[arg(long)]
+    shallow_first: bool,
+
+    /// Stop keeping new results once they take about this much memory, e.g. '500M'
+    ///
+    /// The size is a number followed by K, M or G (powers of 1024), or a number of bytes.
+    /// The memory used is an estimate, and the results found after reaching it are not printed
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    limit_memory: Option<u64>,
+
+    /// Keep at most N matching files, the search continues for the directories
+    ///
+    /// Which N files are kept depends on the order they are found in, so it can change between runs.
+    /// Symlinks and other entries that are not directories count as files
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Keep at most N matching directories, the search continues for the files
+    ///
+    /// Which N directories are kept depends on the order they are found in, so it can change between runs
+    #[arg(long, value_name = "N")]
+    max_dirs: Option<usize>,
+
+    /// Maximum number of directories and files the search keeps open at the same time, for systems with a low 'ulimit -n'
+    ///
+    /// Without it, directories can't be read once the limit of the system is reached, which is reported with '--verbose'.
+    /// Searches are slower with a low limit. Each directory being read takes 2 with '--skip-binary' or '--only-binary'
+    #[arg(long, value_name = "N")]
+    max_open_files: Option<usize>,
+
+    /// Run the search N times and print the minimum, median and maximum times to stderr
+    ///
+    /// The results are not printed. An extra run is done first to warm the filesystem cache
+    #[arg(long, value_name = "N", conflicts_with_all(["first", "select", "multiselect", "progress"]))]
+    benchmark: Option<usize>,
+
+    /// Print the version, platform, compile-time features and options of this build as JSON, then exit without searching
+    ///
+    /// e.g. '{"name":"hunt","version":"3.0.0","os":"linux","family":"unix","arch":"x86_64","features":{"mimalloc":true,"perf":false},"options":["first",...]}'.
+    /// The other options are ignored
+    #[arg(long, visible_alias = "version-json")]
+    capabilities: bool,
+
+    /// Number of threads used by the search, by default the number of CPUs
+    ///
+    /// At least 2 threads are always used, as one of them collects the results
+    #[arg(long, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Search once, then filter the results with the queries read from stdin, one per line, until EOF
+    ///
+    /// The queries are matched like the NAME argument, without walking the directories again.
+    /// When used, all the positional arguments are treated as directories to search in
+    #[arg(
+        long,
+        conflicts_with_all([
+            "first",
+            "select",
+            "multiselect",
+            "siblings",
+            "benchmark",
+            "count_by_extension",
+            "newest",
+            "oldest",
+            "explicit_name",
+            "same_name_as",
+        ])
+    )]
+    interactive: bool,
+
+    /// Prefix of the names of the search threads, which are named '<PREFIX>-<N>'
+    ///
+    /// Useful for telling them apart in tools like 'perf' or 'htop'
+    #[arg(long, value_name = "PREFIX", default_value = "hunt-worker")]
+    thread_name_prefix: String,
+
+    /// Check the NUL-separated paths in this file instead of searching the directories, '-' reads them from stdin
+    ///
+    /// The paths are not traversed, so the listed directories can be found but their contents are skipped.
+    /// Paths that don't exist are skipped, and reported with '--verbose'
+    ///
+    /// e.g. "find . -mtime -1 -print0 | hunt --files0-from - SomeFile"
+    #[arg(long, value_name = "FILE", conflicts_with = "search_in_dirs")]
+    files0_from: Option<PathBuf>,
+
+    /// Search the executables in the '$PATH' directories, like an inexact 'which'
+    ///
+    /// The directories are not searched recursively. On Windows, the executables are the files with an extension in '%PATHEXT%'
+    ///
+    /// e.g. "hunt --path gcc"
+    #[arg(long, conflicts_with_all(["search_in_dirs", "files0_from"]))]
+    path: bool,
+
+    /// Search files/directories with exactly the same name as this path
+    ///
+    /// e.g. "hunt --same-name-as ./config.toml /etc" is the same as "hunt --exact config.toml /etc"
+    ///
+    /// When used, all the positional arguments are treated as directories to search in
+    #[arg(long, value_name = "PATH")]
+    same_name_as: Option<PathBuf>,
+
+    /// Name of the file/folder to search, used exactly as written
+    ///
+    /// Unlike the positional name, it's never treated as a directory when it's "." or contains a path separator.
+    /// When used, all the positional arguments are treated as directories to search in
+    #[arg(long = "name", value_name = "STR", conflicts_with = "same_name_as")]
+    explicit_name: Option<String>,
+
     /// Name of the file/folder to search. If starts/ends are specified, this field can be skipped
+    ///
+    /// If it's "." or contains a path separator, it's treated as a directory to search in instead (use '--name' to avoid it)
     name: Option<String>,
 
     /// Directories where you want to search
@@ -253,6 +1411,10 @@ pub struct Cli {
     /// These directories are treated independently, so if one is nested into another the search will be done two times:
     ///
     /// e.g. "hunt somefile /home/user /home/user/downloads" will search in the home directory, and because /home/user/downloads is inside it, /downloads will be traversed two times
+    ///
+    /// The depth of each directory can be limited by appending ':DEPTH', where 1 means only its direct entries are searched
+    ///
+    /// e.g. "hunt somefile /shallow:1 /deep:10"
     #[arg(required = false)]
     search_in_dirs: Vec<PathBuf>,
 }
@@ -261,32 +1423,122 @@ impl Cli {
     pub fn run() -> Search {
         profi::prof!(cli);
 
-        let cli = Self::parse();
+        let mut cli = Self::parse();
+
+        if cli.capabilities {
+            let printed = crate::print::print_capabilities();
+            std::process::exit(printed.is_err() as i32)
+        }
 
+        let threads = num_threads(cli.threads, std::thread::available_parallelism, cli.verbose);
+        build_thread_pool(std::mem::take(&mut cli.thread_name_prefix), threads);
+        cli.into_search()
+    }
+
+    /// Checks the arguments and builds the search from them, exiting with an error if they are not valid.
+    ///
+    /// The search runs in the current thread pool, which is started by [`Cli::run`].
+    pub fn into_search(self) -> Search {
+        let cli = self;
         let mut search_in_dirs = cli.search_in_dirs;
-        let mut starts = cli.starts_with.unwrap_or_default();
-        let mut ends = cli.ends_with.unwrap_or_default();
-        let ftype = cli.file_type.into();
+        let mut starts = cli.starts_with;
+        let mut ends = cli.ends_with;
+        let mut require_all = cli.require_all;
+        let mut parent = cli.parent;
+        let ftype = if cli.path {
+            FileType::File
+        } else {
+            cli.file_type.into()
+        };
+        let group = match (cli.dirs_first, cli.files_first) {
+            (true, _) => Group::DirsFirst,
+            (_, true) => Group::FilesFirst,
+            _ => Group::Mixed,
+        };
 
-        let name = match cli.name {
+        // With --same-name-as, --name or --interactive all the positional arguments are directories
+        let only_dirs =
+            cli.same_name_as.is_some() || cli.explicit_name.is_some() || cli.interactive;
+        let mut name = match cli.name {
             // If directory is given but no file name is specified, print files in that directory
             // ex. hunt /home/user
             Some(n) if n == "." || n.contains(std::path::MAIN_SEPARATOR) => {
                 search_in_dirs.insert(0, PathBuf::from(n));
                 String::new()
             }
+            Some(n) if only_dirs => {
+                search_in_dirs.insert(0, PathBuf::from(n));
+                String::new()
+            }
             Some(n) => n,
             None => String::new(),
         };
+        if let Some(n) = cli.explicit_name {
+            name = n;
+        }
+        if let Some(path) = &cli.same_name_as {
+            let Some(file_name) = path.file_name() else {
+                eprintln!("Error: {:?} does not have a file name", path);
+                std::process::exit(1)
+            };
+            name = file_name.to_string_lossy().into_owned();
+        }
 
-        let case_sensitive =
-            cli.case_sensitive || name.contains(|c: char| c.is_alphabetic() && c.is_uppercase());
+        let has_uppercase = |s: &str| s.contains(|c: char| c.is_alphabetic() && c.is_uppercase());
+        // The case flags take precedence over the filesystem, so do explicit smart case
+        let (case_flag, ignore_flag) = match (cli.case_sensitive, cli.ignore_case, cli.fs_case) {
+            (false, false, FsCase::Sensitive) => (true, false),
+            (false, false, FsCase::Insensitive) if !cli.smart_case => (false, true),
+            #[cfg(windows)]
+            (false, false, FsCase::Auto) if !cli.smart_case => (false, true),
+            (case_sensitive, ignore_case, _) => (case_sensitive, ignore_case),
+        };
+        let smart_case = !cli.no_smart_case && !case_flag && !ignore_flag;
+        let case_sensitive = is_case_sensitive(
+            case_flag,
+            ignore_flag,
+            smart_case,
+            has_uppercase(&name)
+                || starts.iter().any(|s| has_uppercase(s))
+                || ends.iter().any(|e| has_uppercase(e))
+                || require_all.iter().any(|r| has_uppercase(r))
+                || parent.as_deref().is_some_and(has_uppercase),
+        );
         if !case_sensitive {
-            starts.make_ascii_lowercase();
-            ends.make_ascii_lowercase();
+            name.make_ascii_lowercase();
+            starts.iter_mut().for_each(|s| s.make_ascii_lowercase());
+            ends.iter_mut().for_each(|e| e.make_ascii_lowercase());
+            for substring in &mut require_all {
+                substring.make_ascii_lowercase();
+            }
+            if let Some(parent) = &mut parent {
+                parent.make_ascii_lowercase();
+            }
         }
 
-        let mut ignore_dirs = cli.ignore.unwrap_or_default();
+        // Absolute paths with glob metacharacters are matched as globs
+        let is_glob = |p: &PathBuf| p.is_absolute() && p.to_string_lossy().contains(GLOB_CHARS);
+        let ignore = cli.ignore.unwrap_or_default().into_iter();
+        let (ignore_globs, mut ignore_dirs): (Vec<_>, Vec<_>) = ignore.partition(is_glob);
+        let explicit_ignore_globs = if ignore_globs.is_empty() {
+            None
+        } else {
+            let mut builder = globset::GlobSetBuilder::new();
+            for p in &ignore_globs {
+                let glob = globset::GlobBuilder::new(&p.to_string_lossy())
+                    .literal_separator(!cli.no_literal_separator)
+                    .build()
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: Invalid ignore pattern {:?}: {e}", p);
+                        std::process::exit(1)
+                    });
+                builder.add(glob);
+            }
+            Some(builder.build().unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                std::process::exit(1)
+            }))
+        };
         // canonicalize non global paths
         // ./Cargo.toml => canonicalized
         // /home/user//Cargo.toml
@@ -297,23 +1549,636 @@ impl Cli {
             }
         }
         
+        let load_rules = |files: &[PathBuf]| {
+            if files.is_empty() {
+                return None;
+            }
+            match crate::ignorefile::IgnoreFile::new(files, !cli.no_literal_separator) {
+                Ok(rules) => Some(rules),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1)
+                }
+            }
+        };
+        let ignore_file = load_rules(&cli.ignore_file);
+        let glob_from = load_rules(&cli.glob_from);
+
+        let mut search_dirs = search_in_dirs
+            .into_iter()
+            .map(parse_depth_limit)
+            .collect::<Vec<_>>();
+        if cli.path {
+            search_dirs.extend(path_dirs().into_iter().map(|dir| (dir, Some(1))));
+        }
+        // Missing directories are skipped silently, unless none of them exist
+        if cli.no_messages && !search_dirs.is_empty() {
+            search_dirs.retain(|(dir, _)| dir.exists());
+            if search_dirs.is_empty() {
+                eprintln!("Error: None of the search directories exist");
+                std::process::exit(1)
+            }
+        }
+
+        if let (Some(min), Some(max)) = (cli.min_size, cli.max_size) {
+            if min > max {
+                eprintln!("Error: '--min-size' is bigger than '--max-size', no file can be found");
+                std::process::exit(1)
+            }
+        }
+        let per_dir = 1 + (cli.skip_binary || cli.only_binary) as usize;
+        if cli.max_open_files.is_some_and(|n| n < per_dir) {
+            eprintln!("Error: '--max-open-files' must be at least {per_dir} to read any directory");
+            std::process::exit(1)
+        }
+
+        let modified_after = cli.since_file.map(|path| {
+            std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: Could not read the mtime of {:?}: {e}", path);
+                    std::process::exit(1)
+                })
+        });
+
+        let files = cli.files0_from.map(|path| {
+            read_files0(&path).unwrap_or_else(|e| {
+                eprintln!("Error: Could not read {:?}: {e}", path);
+                std::process::exit(1)
+            })
+        });
+
+        let (theme_name, theme_affix) = cli.color_theme.highlights();
         Search::new(
             cli.first,
-            cli.exact,
+            cli.exact
+                || cli.exact_stem
+                || cli.same_name_as.is_some()
+                || cli.only == Some(Only::Exact),
+            cli.exact_stem,
+            cli.anchored,
+            cli.only,
             cli.canonicalize,
+            cli.resolve_output,
             case_sensitive,
-            !search_in_dirs.is_empty(),
+            smart_case,
+            !search_dirs.is_empty() || cli.path,
             cli.verbose,
-            cli.hidden,
+            cli.report_skipped,
+            cli.hidden || cli.traverse_hidden,
+            cli.hidden || cli.match_hidden,
             cli.select,
             cli.multiselect,
-            cli.simple,
+            cli.select_filter,
+            cli.output_file,
+            cli.append,
+            cli.output_encoding,
+            cli.output_buffer_size.map(|n| n as usize),
+            group,
+            cli.sort,
+            cli.reverse,
+            cli.natural_sort,
+            cli.collapse,
+            cli.deterministic,
+            cli.newest,
+            cli.oldest,
+            cli.trailing_slash,
+            cli.path_separator,
+            cli.trim.and_then(|width| match width {
+                Width::Columns(n) => Some(n),
+                Width::Auto => terminal_width().map(|w| w.max(MIN_TRIM_WIDTH)),
+            }),
+            cli.quote_style,
+            cli.show_depth,
+            cli.inode,
+            cli.show_type,
+            cli.git_status,
+            cli.show_offsets,
+            cli.relative_to,
+            cli.relative_roots,
+            cli.match_roots,
+            cli.siblings,
+            cli.unique,
+            cli.follow_under,
+            cli.follow_max_depth,
+            cli.progress,
+            cli.ls_colors,
+            cli.color_name.unwrap_or(theme_name),
+            cli.color_affix.unwrap_or(theme_affix),
+            cli.simple.max(cli.show_offsets as u8),
             name,
             starts,
             ends,
+            require_all,
+            affix_regex(cli.starts_regex.as_deref(), Anchor::Start, case_sensitive),
+            affix_regex(cli.ends_regex.as_deref(), Anchor::End, case_sensitive),
+            parent,
             ftype,
+            cli.path,
+            cli.exclude_ext
+                .into_iter()
+                .map(|e| e.trim_start_matches('.').to_owned())
+                .collect(),
             ignore_dirs,
-            search_in_dirs,
+            explicit_ignore_globs,
+            ignore_file,
+            glob_from,
+            !cli.no_default_ignore,
+            !cli.no_skip_git,
+            cli.exclude_dir,
+            search_dirs,
+            files,
+            cli.accessed_within,
+            cli.accessed_before,
+            cli.mtime_matches,
+            cli.time_format,
+            cli.time_utc,
+            modified_after,
+            cli.min_size,
+            cli.max_size,
+            match (cli.skip_binary, cli.only_binary) {
+                (true, _) => Some(false),
+                (_, true) => Some(true),
+                _ => None,
+            },
+            cli.min_results,
+            cli.count_by_extension,
+            cli.du,
+            cli.summarize_above,
+            cli.total_size,
+            cli.size_units,
+            cli.summary_json,
+            cli.max_time,
+            cli.shallow_first,
+            cli.limit_memory,
+            cli.max_files,
+            cli.max_dirs,
+            cli.max_open_files,
+            cli.interactive,
+            cli.benchmark,
         )
     }
 }
+
+/// Returns the number of threads of the search pool, `threads` or the available parallelism.
+///
+/// The results are received in one of the pool threads, so at least 2 are needed for the search to make progress.
+fn num_threads(
+    threads: Option<usize>,
+    parallelism: impl FnOnce() -> std::io::Result<std::num::NonZeroUsize>,
+    verbose: bool,
+) -> usize {
+    let threads = threads.unwrap_or_else(|| match parallelism() {
+        Ok(n) => n.get(),
+        Err(e) => {
+            if verbose {
+                eprintln!("Could not detect the number of CPUs ({e}), using the minimum number of threads");
+            }
+            1
+        }
+    });
+    threads.max(2)
+}
+
+/// Starts the global thread pool used by the search, naming its threads '{prefix}-{i}'.
+fn build_thread_pool(prefix: String, threads: usize) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |i| format!("{prefix}-{i}"))
+        .build_global();
+    if let Err(e) = pool {
+        eprintln!("Error: Could not start the search threads: {e}");
+        std::process::exit(1)
+    }
+}
+
+/// Characters that make an absolute `--ignore` path a glob.
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+/// Decides if the search is case sensitive.
+///
+/// '--case-sensitive' and '--ignore-case' always win, otherwise it's only case sensitive with smart case and an uppercase letter in the query.
+fn is_case_sensitive(
+    case_sensitive: bool,
+    ignore_case: bool,
+    smart_case: bool,
+    has_uppercase: bool,
+) -> bool {
+    match (case_sensitive, ignore_case) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => smart_case && has_uppercase,
+    }
+}
+
+/// Splits the ':DEPTH' suffix of a search directory, if it has one.
+///
+/// Paths that exist as written are never split, so directories containing ':' can still be searched.
+fn parse_depth_limit(dir: PathBuf) -> (PathBuf, Option<usize>) {
+    if dir.exists() {
+        return (dir, None);
+    }
+    let split = dir.to_str().and_then(|d| {
+        let (path, depth) = d.rsplit_once(':')?;
+        Some((PathBuf::from(path), depth.parse().ok()?))
+    });
+    match split {
+        Some((path, depth)) if !path.as_os_str().is_empty() => (path, Some(depth)),
+        _ => (dir, None),
+    }
+}
+
+/// Returns the existing directories of `$PATH`, without duplicates and in order.
+fn path_dirs() -> Vec<PathBuf> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut dirs = Vec::<PathBuf>::new();
+    for dir in std::env::split_paths(&path) {
+        if dir.is_dir() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Reads the NUL-separated paths of `path`, or of stdin if it's '-'.
+fn read_files0(path: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
+    let content = if path == std::path::Path::new("-") {
+        let mut content = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin().lock(), &mut content)?;
+        content
+    } else {
+        std::fs::read(path)?
+    };
+    let to_path = |bytes: &[u8]| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+        }
+        #[cfg(not(unix))]
+        {
+            PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+        }
+    };
+    Ok(content
+        .split(|&b| b == 0)
+        .filter(|p| !p.is_empty())
+        .map(to_path)
+        .collect())
+}
+
+/// Narrowest width accepted by `--trim`, enough for "..." and a short file name.
+const MIN_TRIM_WIDTH: usize = 8;
+
+/// Width given to `--trim`.
+#[derive(Clone, Copy, Debug)]
+pub enum Width {
+    Auto,
+    Columns(usize),
+}
+
+fn parse_width(s: &str) -> Result<Width, String> {
+    if s == "auto" {
+        return Ok(Width::Auto);
+    }
+    let n = s
+        .parse()
+        .map_err(|_| format!("Expected a number of characters or 'auto', found '{s}'"))?;
+    if n < MIN_TRIM_WIDTH {
+        return Err(format!("The minimum width is {MIN_TRIM_WIDTH}, found {n}"));
+    }
+    Ok(Width::Columns(n))
+}
+
+/// How a part of the highlighted results is painted, given to `--color-name` and `--color-affix`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Highlight {
+    /// Not highlighted, 'none'
+    None,
+    /// Bold without changing its color, 'bold'
+    Bold,
+    /// Bold and with this color
+    Color(colored::Color),
+}
+
+fn parse_color(s: &str) -> Result<Highlight, String> {
+    if s.eq_ignore_ascii_case("none") {
+        return Ok(Highlight::None);
+    }
+    if s.eq_ignore_ascii_case("bold") {
+        return Ok(Highlight::Bold);
+    }
+    // colored expects "bright red", but spaces are awkward in the command line
+    let name = s.replace(['-', '_'], " ");
+    match name.parse() {
+        Ok(color) => Ok(Highlight::Color(color)),
+        Err(()) => Err(format!(
+            "Unknown color '{s}', expected e.g. 'red', 'bright-blue', 'bold' or 'none'"
+        )),
+    }
+}
+
+/// Width of the terminal the results are printed to, if they are printed to one.
+fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size()
+        .ok()
+        .map(|(columns, _)| columns as usize)
+}
+
+/// Parses a size like '512', '64K', '500M' or '2G', with binary units.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n
+        .parse()
+        .map_err(|_| format!("'{s}' is not a valid size, expected e.g. '500M' or '2G'"))?;
+    let multiplier = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return Err(format!("Unknown unit '{unit}', use K, M or G")),
+    };
+    n.checked_mul(multiplier)
+        .ok_or_else(|| format!("'{s}' is too big"))
+}
+
+/// Checks that `s` is a valid regex, the anchored regex is built once the case sensitivity is known.
+fn parse_regex(s: &str) -> Result<String, String> {
+    regex_automata::meta::Regex::new(s).map_err(|e| match e.syntax_error() {
+        Some(syntax) => syntax.to_string(),
+        None => e.to_string(),
+    })?;
+    Ok(s.to_owned())
+}
+
+/// Builds the regex of `--starts-regex` or `--ends-regex`, anchored at the start or the end of the name.
+fn affix_regex(
+    pattern: Option<&str>,
+    anchor: Anchor,
+    case_sensitive: bool,
+) -> Option<regex_automata::meta::Regex> {
+    let pattern = match anchor {
+        Anchor::Start => format!("^(?:{})", pattern?),
+        _ => format!("(?:{})$", pattern?),
+    };
+    let syntax = regex_automata::util::syntax::Config::new().case_insensitive(!case_sensitive);
+    let regex = regex_automata::meta::Regex::builder()
+        .syntax(syntax)
+        .build(&pattern);
+    Some(regex.expect("the regex was validated when parsing the arguments"))
+}
+
+/// Checks that `s` is a valid strftime format, so formatting the times can't fail during the search.
+fn parse_time_format(s: &str) -> Result<String, String> {
+    use chrono::format::{Item, StrftimeItems};
+    if StrftimeItems::new(s).any(|item| item == Item::Error) {
+        return Err(format!("'{s}' is not a valid strftime format"));
+    }
+    Ok(s.to_owned())
+}
+
+/// Parses a duration like '30s', '10m', '2h', '3d' or '1w'.
+///
+/// A number without unit is interpreted as seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n
+        .parse()
+        .map_err(|_| format!("'{s}' is not a valid duration, expected e.g. '30m' or '2d'"))?;
+    let secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => return Err(format!("Unknown unit '{unit}', use s, m, h, d or w")),
+    };
+    Ok(Duration::from_secs(n.saturating_mul(secs)))
+}
+
+#[cfg(test)]
+impl Search {
+    /// Builds the search of the `hunt` command line `args`, without starting the thread pool.
+    pub fn from_args(args: &[&str]) -> Self {
+        let args = std::iter::once("hunt").chain(args.iter().copied());
+        Cli::try_parse_from(args).unwrap().into_search()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    #[test]
+    fn uppercase_affixes_make_the_search_case_sensitive() {
+        let search = Search::from_args(&["--smart-case", "--starts", "README", "foo"]);
+        assert!(search.case_sensitive);
+        assert_eq!(
+            (search.starts, search.name),
+            (vec!["README".to_owned()], "foo".to_owned())
+        );
+        let search = Search::from_args(&["--smart-case", "--ends", "Foo", "readme"]);
+        assert!(search.case_sensitive);
+
+        let search = Search::from_args(&["--smart-case", "--starts", "readme", "foo"]);
+        assert!(!search.case_sensitive);
+        // Without smart case, the query and the affixes are lowercased together
+        let search = Search::from_args(&["-I", "--starts", "README", "Foo"]);
+        assert_eq!(
+            (search.starts, search.name),
+            (vec!["readme".to_owned()], "foo".to_owned())
+        );
+    }
+
+    #[test]
+    fn case_flags_win_over_smart_case() {
+        for has_uppercase in [false, true] {
+            for smart_case in [false, true] {
+                assert!(is_case_sensitive(true, false, smart_case, has_uppercase));
+                assert!(!is_case_sensitive(false, true, smart_case, has_uppercase));
+            }
+            assert!(!is_case_sensitive(false, false, false, has_uppercase));
+            assert_eq!(
+                is_case_sensitive(false, false, true, has_uppercase),
+                has_uppercase
+            );
+        }
+    }
+
+    // On Windows the searches are case-insensitive by default, see '--fs-case'
+    #[cfg(not(windows))]
+    #[test]
+    fn case_sensitivity_of_every_flag_combination() {
+        // The flags given and if the search is case sensitive with the queries "foo" and "Foo"
+        let cases: [(&[&str], bool, bool); 8] = [
+            (&[], false, true),
+            (&["-C"], true, true),
+            (&["-I"], false, false),
+            (&["-C", "-I"], false, false),
+            (&["--no-smart-case"], false, false),
+            (&["-C", "--no-smart-case"], true, true),
+            (&["-I", "--no-smart-case"], false, false),
+            (&["-C", "-I", "--no-smart-case"], false, false),
+        ];
+        for (flags, lowercase, mixed_case) in cases {
+            for (query, expected) in [("foo", lowercase), ("Foo", mixed_case)] {
+                let args = [flags, &[query]].concat();
+                let search = Search::from_args(&args);
+                assert_eq!(search.case_sensitive, expected, "{args:?}");
+                let name = if expected { query } else { "foo" };
+                assert_eq!(search.name, name, "{args:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn uppercase_starts_matches_case_sensitively() {
+        let dir = TestDir::new(
+            "uppercase-starts",
+            &["README-foo", "readme-foo", "README-FOO"],
+        );
+        let search = Search::from_args(&["--smart-case", "--starts", "README", "foo", dir.arg()]);
+        assert_eq!(dir.found_paths(&search), ["README-foo"]);
+        let search = Search::from_args(&["--smart-case", "--starts", "readme", "foo", dir.arg()]);
+        assert_eq!(
+            dir.found_paths(&search),
+            ["README-FOO", "README-foo", "readme-foo"]
+        );
+    }
+
+    #[test]
+    fn depth_limit_suffix() {
+        let split = |dir: &str| parse_depth_limit(PathBuf::from(dir));
+        assert_eq!(split("src:2"), (PathBuf::from("src"), Some(2)));
+        assert_eq!(split("/a/b:0"), (PathBuf::from("/a/b"), Some(0)));
+        assert_eq!(split("src"), (PathBuf::from("src"), None));
+        assert_eq!(split("src:deep"), (PathBuf::from("src:deep"), None));
+        assert_eq!(split(":2"), (PathBuf::from(":2"), None));
+
+        // Existing paths are never split
+        let dir = TestDir::new("depth-limit-suffix", &["dir:2/"]);
+        let existing = format!("{}/dir:2", dir.arg());
+        assert_eq!(split(&existing), (PathBuf::from(&existing), None));
+    }
+
+    #[test]
+    fn size_suffixes() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size("1k"), Ok(1024));
+        assert_eq!(parse_size("1KiB"), Ok(1024));
+        assert_eq!(parse_size("500M"), Ok(500 << 20));
+        assert_eq!(parse_size("2gb"), Ok(2 << 30));
+        assert_eq!(parse_size("10B"), Ok(10));
+        assert!(parse_size("1T").is_err());
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("1.5M").is_err());
+        assert!(parse_size("99999999999G").is_err());
+    }
+
+    #[test]
+    fn num_threads_without_parallelism() {
+        let unknown = || Err(std::io::Error::other("no CPU count"));
+        assert_eq!(num_threads(None, unknown, false), 2);
+        let one = || Ok(std::num::NonZeroUsize::MIN);
+        assert_eq!(num_threads(None, one, false), 2);
+        let eight = || Ok(std::num::NonZeroUsize::new(8).unwrap());
+        assert_eq!(num_threads(None, eight, false), 8);
+    }
+
+    #[test]
+    fn threads_override_parallelism() {
+        let eight = || Ok(std::num::NonZeroUsize::new(8).unwrap());
+        assert_eq!(num_threads(Some(3), eight, false), 3);
+        let unreachable = || -> std::io::Result<std::num::NonZeroUsize> { unreachable!() };
+        assert_eq!(num_threads(Some(4), unreachable, false), 4);
+        // The receiver takes one of the threads, so a single one is not enough
+        assert_eq!(num_threads(Some(1), eight, false), 2);
+    }
+
+    #[test]
+    fn default_ignore_list() {
+        let mut names = HARDCODED_IGNORE.iter().copied().collect::<Vec<_>>();
+        names.sort_unstable();
+        let expected = [
+            ".cache",
+            ".git",
+            "Library",
+            "__pycache__",
+            "node_modules",
+            "target",
+        ];
+        assert_eq!(names, expected);
+        assert!(!HARDCODED_IGNORE.contains("Target"));
+
+        let search = Search::from_args(&["foo"]);
+        assert!(search.hardcoded_ignore.contains("target"));
+        let search = Search::from_args(&["--no-default-ignore", "foo"]);
+        assert!(search.hardcoded_ignore.is_empty());
+        let search = Search::from_args(&["--hidden", "foo"]);
+        assert!(search.hardcoded_ignore.is_empty());
+    }
+
+    #[test]
+    fn trim_width_has_a_minimum() {
+        assert!(matches!(parse_width("auto"), Ok(Width::Auto)));
+        assert!(matches!(parse_width("8"), Ok(Width::Columns(8))));
+        assert!(matches!(parse_width("80"), Ok(Width::Columns(80))));
+        assert!(parse_width("3").is_err());
+        assert!(parse_width("0").is_err());
+        assert!(parse_width("wide").is_err());
+    }
+
+    #[test]
+    fn invalid_affix_regex_is_rejected() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args).map(|_| ());
+        assert!(parse(&["hunt", "--ends-regex", r"\.(jpe?g|png"]).is_err());
+        assert!(parse(&["hunt", "--starts-regex", "[a-"]).is_err());
+        assert!(parse(&["hunt", "--ends-regex", r"\.(jpe?g|png)"]).is_ok());
+    }
+
+    #[test]
+    fn affix_regex_is_anchored() {
+        let ends = affix_regex(Some("jpe?g|png"), Anchor::End, true).unwrap();
+        assert!(ends.is_match("a.png"));
+        assert!(ends.is_match("a.jpeg"));
+        assert!(!ends.is_match("png.gif"));
+        let starts = affix_regex(Some("jpe?g|png"), Anchor::Start, true).unwrap();
+        assert!(starts.is_match("png.gif"));
+        assert!(!starts.is_match("a.png"));
+        assert!(affix_regex(None, Anchor::Start, true).is_none());
+    }
+
+    #[test]
+    fn no_messages_skips_missing_directories() {
+        let dir = TestDir::new("no-messages", &["a.txt"]);
+        let missing = dir.join("missing");
+        let args = ["--no-messages", "a", missing.to_str().unwrap(), dir.arg()];
+        let search = Search::from_args(&args);
+        assert_eq!(search.dirs, [(PathBuf::from(dir.arg()), None)]);
+        assert_eq!(dir.found_paths(&search), ["a.txt"]);
+    }
+
+    #[test]
+    fn invalid_time_format_is_rejected() {
+        assert!(parse_time_format("%Y-%m-%d %H:%M").is_ok());
+        assert!(parse_time_format("%Y-%").is_err());
+        let args = [
+            "hunt",
+            "--mtime-matches",
+            "2023",
+            "--time-format",
+            "%Y-%",
+            "",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{searchresult::SearchResult, structs::Search};
+use std::path::PathBuf;
+
+/// Directory tree created for a test, removed when dropped.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates a directory for the test `name` with the `entries` inside.
+    ///
+    /// Entries ending in '/' are created as directories, the rest as empty files.
+    pub fn new(name: &str, entries: &[&str]) -> Self {
+        let dir = std::env::temp_dir().join(format!("hunt-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = Self(dir.canonicalize().unwrap());
+        for entry in entries {
+            dir.create(entry);
+        }
+        dir
+    }
+
+    /// Creates `entry` in the tree, a directory if it ends in '/' and an empty file otherwise.
+    pub fn create(&self, entry: &str) {
+        let path = self.0.join(entry);
+        if entry.ends_with('/') {
+            std::fs::create_dir_all(path).unwrap();
+        } else {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+    }
+
+    /// Path of `entry` in the tree.
+    pub fn join(&self, entry: &str) -> PathBuf {
+        self.0.join(entry)
+    }
+
+    /// Path of the tree as a command line argument.
+    pub fn arg(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+
+    /// Runs `search` and returns the results with their paths relative to the tree, sorted.
+    ///
+    /// The separators are always '/', and the results outside of the tree are kept as found.
+    pub fn found(&self, search: &Search) -> Vec<(String, SearchResult)> {
+        let found = std::sync::Mutex::new(Vec::new());
+        search.search_with_sink(&|result| found.lock().unwrap().push(result));
+        let mut found = found.into_inner().unwrap();
+        found.sort_unstable_by(|a, b| a.entry().cmp(b.entry()));
+        let relative = |result: SearchResult| {
+            let path = result.entry().path.to_string();
+            let path = path.strip_prefix(self.arg()).unwrap_or(&path);
+            let path = path.trim_start_matches(std::path::is_separator);
+            (path.replace(std::path::MAIN_SEPARATOR, "/"), result)
+        };
+        found.into_iter().map(relative).collect()
+    }
+
+    /// Runs `search` and returns the lines it prints, with the paths in the tree relative to it.
+    ///
+    /// The `search` must print to the `--output-file` given by [`TestDir::output_file`].
+    pub fn printed(&self, search: &Search) -> Vec<String> {
+        let (mut exact, mut contains, mut siblings) = (Vec::new(), Vec::new(), Vec::new());
+        for (_, result) in self.found(search) {
+            match result {
+                SearchResult::Exact(entry) => exact.push(entry),
+                SearchResult::Contains(entry) => contains.push(entry),
+                SearchResult::Sibling(entry) => siblings.push(entry),
+            }
+        }
+        search.print_results((exact, contains, siblings)).unwrap();
+        let printed = std::fs::read_to_string(self.output_file()).unwrap();
+        let prefix = format!("{}{}", self.arg(), std::path::MAIN_SEPARATOR);
+        let lines = printed
+            .lines()
+            .map(|line| strip_ansi(line).replace(&prefix, ""));
+        lines
+            .map(|line| line.replace(std::path::MAIN_SEPARATOR, "/"))
+            .collect()
+    }
+
+    /// File outside of the tree the results are printed to with `--output-file`.
+    pub fn output_file(&self) -> String {
+        format!("{}.out", self.arg())
+    }
+
+    /// Runs `search` and returns the paths of the results relative to the tree, sorted.
+    pub fn found_paths(&self, search: &Search) -> Vec<String> {
+        let found = self.found(search).into_iter();
+        found.map(|(path, _)| path).collect()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+        let _ = std::fs::remove_file(self.output_file());
+    }
+}
+
+/// Removes the color escape codes of `s`.
+pub fn strip_ansi(s: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}